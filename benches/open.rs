@@ -0,0 +1,26 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use midi_oxidizer::MidiFile;
+use std::fs::File;
+use std::io::BufReader;
+
+const SAMPLES: [&str; 2] = ["samples/JustAnotherDay.mid", "samples/bachinv1.mid"];
+
+fn open_vs_mmap(c: &mut Criterion) {
+    for path in SAMPLES {
+        let mut group = c.benchmark_group(path);
+        group.bench_function("open", |b| b.iter(|| MidiFile::open(path).unwrap()));
+        group.bench_function("read_bufreader", |b| {
+            b.iter(|| MidiFile::read(BufReader::new(File::open(path).unwrap())).unwrap())
+        });
+        group.bench_function("read_unbuffered", |b| {
+            b.iter(|| MidiFile::read(File::open(path).unwrap()).unwrap())
+        });
+        group.bench_function("open_mmap", |b| {
+            b.iter(|| MidiFile::open_mmap(path).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, open_vs_mmap);
+criterion_main!(benches);
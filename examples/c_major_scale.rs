@@ -0,0 +1,38 @@
+//! Build a one-octave C major scale with MidiBuilder and save it as a MIDI file.
+//!
+//! Usage: cargo run --example c_major_scale [output.mid]
+
+use midi_oxidizer::{note_from_name, Channel, MidiBuilder};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "c_major.mid".to_string());
+
+    const TICKS_PER_QUARTER: u16 = 480;
+    let channel = Channel::new(0).unwrap();
+    let mut builder = MidiBuilder::new(TICKS_PER_QUARTER)?
+        .add_track()
+        .tempo(0, 500_000); // 120 BPM
+
+    // One quarter note per scale degree
+    for name in ["C4", "D4", "E4", "F4", "G4", "A4", "B4", "C5"] {
+        let note = note_from_name(name).unwrap();
+        builder = builder.note_on(0, channel, note, 100).note_off(
+            TICKS_PER_QUARTER as u32,
+            channel,
+            note,
+            64,
+        );
+    }
+
+    let midi_file = builder.build()?;
+    midi_file.save(&path)?;
+    println!(
+        "Wrote {} ({} events)",
+        path,
+        midi_file.tracks[0].events.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use midi_oxidizer::MidiFile;
+
+// Parsing arbitrary bytes must return Ok or Err, never panic
+fuzz_target!(|data: &[u8]| {
+    let _ = MidiFile::from_bytes(data);
+    let _ = MidiFile::from_bytes_lenient(data);
+});
@@ -0,0 +1,37 @@
+use crate::timing::round;
+use crate::MidiMessage;
+
+/// Largest upward and downward pitch bend values, around the centre at 0
+const BEND_UP_MAX: f32 = 8191.0;
+const BEND_DOWN_MAX: f32 = 8192.0;
+
+impl MidiMessage {
+    /// Pitch bend as -1.0 (fully down) to 1.0 (fully up), or None for other messages
+    pub fn bend_normalized(&self) -> Option<f32> {
+        match *self {
+            MidiMessage::PitchBendChange { value, .. } if value >= 0 => {
+                Some((value as f32 / BEND_UP_MAX).min(1.0))
+            }
+            MidiMessage::PitchBendChange { value, .. } => {
+                Some((value as f32 / BEND_DOWN_MAX).max(-1.0))
+            }
+            _ => None,
+        }
+    }
+
+    /// Pitch bend in semitones, given the receiver's bend range in semitones (often 2.0)
+    pub fn bend_semitones(&self, range: f32) -> Option<f32> {
+        self.bend_normalized().map(|bend| bend * range)
+    }
+
+    /// Raw PitchBendChange value for a bend from -1.0 to 1.0, clamping values outside it
+    pub fn bend_from_normalized(normalized: f32) -> i16 {
+        let normalized = normalized.clamp(-1.0, 1.0);
+        let max = if normalized >= 0.0 {
+            BEND_UP_MAX
+        } else {
+            BEND_DOWN_MAX
+        };
+        round((normalized * max) as f64) as i16
+    }
+}
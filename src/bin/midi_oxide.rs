@@ -0,0 +1,116 @@
+use midi_oxidizer::{Channel, MidiFile, MidiMessage};
+
+const USAGE: &str =
+    "Usage: midi_oxide [FILE]\n       midi_oxide dump FILE [--track N] [--channel N]";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("dump") {
+        return dump(&args[2..]);
+    }
+
+    // Check if a path was provided as an argument
+    let path = if args.len() > 1 {
+        &args[1]
+    } else {
+        // Default to a sample path if none provided
+        "example.mid"
+    };
+
+    println!("Opening MIDI file: {}", path);
+
+    // Open and parse the MIDI file
+    let midi_file = MidiFile::open(path)?;
+
+    // Display MIDI file information
+    println!("MIDI file format: {}", midi_file.header.format);
+    println!("Number of tracks: {}", midi_file.header.num_tracks);
+    println!("Time division: {}", midi_file.header.time_division);
+
+    // Display information about each track
+    for (i, track) in midi_file.tracks.iter().enumerate() {
+        println!("Track {}: {} events", i, track.events.len());
+
+        // Count note events (optional)
+        let note_on_count = track
+            .events
+            .iter()
+            .filter(|e| matches!(e.message, MidiMessage::NoteOn { .. }))
+            .count();
+
+        println!("  Contains {} note-on events", note_on_count);
+    }
+
+    Ok(())
+}
+
+/// Print every event of a file with its absolute tick, one per line.
+///
+/// `--track N` keeps only track N (zero-based) and `--channel N` only channel
+/// messages on channel N (1-16, as displayed).
+fn dump(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut path = None;
+    let mut track_filter = None;
+    let mut channel_filter = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--track" => track_filter = Some(option_value::<usize>(args.next(), "--track")),
+            "--channel" => {
+                let channel = option_value::<u8>(args.next(), "--channel");
+                match channel.checked_sub(1).and_then(Channel::new) {
+                    Some(channel) => channel_filter = Some(channel),
+                    None => usage_error(&format!("--channel must be 1-16, got {}", channel)),
+                }
+            }
+            _ if path.is_none() => path = Some(arg.as_str()),
+            _ => usage_error(&format!("unexpected argument {:?}", arg)),
+        }
+    }
+    let Some(path) = path else {
+        usage_error("no file given");
+    };
+
+    let midi_file = MidiFile::open(path)?;
+    let num_tracks = midi_file.tracks.len();
+    if let Some(track) = track_filter.filter(|&track| track >= num_tracks) {
+        let valid = match num_tracks {
+            0 => "the file has no tracks".to_string(),
+            n => format!("valid tracks are 0-{}", n - 1),
+        };
+        eprintln!("Error: --track {} is out of range; {}", track, valid);
+        std::process::exit(1);
+    }
+
+    for (i, track) in midi_file.tracks.iter().enumerate() {
+        if track_filter.is_some_and(|t| t != i) {
+            continue;
+        }
+
+        println!("Track {}", i);
+        for (tick, event) in track.iter_absolute() {
+            if channel_filter.is_some_and(|c| event.message.channel() != Some(c)) {
+                continue;
+            }
+            println!("{:>8} {}", tick, event.message);
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse the value following a command-line option
+fn option_value<T: std::str::FromStr>(value: Option<&String>, option: &str) -> T {
+    match value.and_then(|v| v.parse().ok()) {
+        Some(value) => value,
+        None => usage_error(&format!("{} needs a numeric value", option)),
+    }
+}
+
+/// Report a command-line mistake with the usage text and exit
+fn usage_error(message: &str) -> ! {
+    eprintln!("Error: {}\n{}", message, USAGE);
+    std::process::exit(2);
+}
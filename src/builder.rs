@@ -0,0 +1,218 @@
+use crate::{
+    Channel, MetaEvent, MidiError, MidiEvent, MidiFile, MidiHeader, MidiMessage, MidiTrack,
+};
+use alloc::format;
+use alloc::vec::Vec;
+
+/// Incrementally builds a MidiFile, one track at a time.
+///
+/// Events are appended to the most recently added track (a first track is created on
+/// demand) and every track is closed with an EndOfTrack when the file is built.
+#[derive(Debug, Clone)]
+pub struct MidiBuilder {
+    time_division: u16,
+    tracks: Vec<MidiTrack>,
+}
+
+impl MidiBuilder {
+    /// Start a file timed in `ticks_per_quarter` ticks per quarter note, which must be
+    /// from 1 to 0x7FFF
+    pub fn new(ticks_per_quarter: u16) -> Result<Self, MidiError> {
+        if !(1..=0x7FFF).contains(&ticks_per_quarter) {
+            return Err(MidiError::InvalidOperation(format!(
+                "Ticks per quarter note must be from 1 to 32767, got {}",
+                ticks_per_quarter
+            )));
+        }
+        Ok(MidiBuilder {
+            time_division: ticks_per_quarter,
+            tracks: Vec::new(),
+        })
+    }
+
+    /// Start a new track; following events are added to it
+    pub fn add_track(mut self) -> Self {
+        self.tracks.push(MidiTrack { events: Vec::new() });
+        self
+    }
+
+    /// Append a message `delta` ticks after the previous event on the current track
+    pub fn event(mut self, delta: u32, message: MidiMessage) -> Self {
+        if self.tracks.is_empty() {
+            self = self.add_track();
+        }
+        let track = self.tracks.last_mut().unwrap();
+        track.events.push(MidiEvent {
+            delta_time: delta,
+            message,
+        });
+        self
+    }
+
+    /// Append a NoteOn
+    pub fn note_on(self, delta: u32, channel: Channel, note: u8, velocity: u8) -> Self {
+        self.event(
+            delta,
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            },
+        )
+    }
+
+    /// Append a NoteOff
+    pub fn note_off(self, delta: u32, channel: Channel, note: u8, velocity: u8) -> Self {
+        self.event(
+            delta,
+            MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity,
+            },
+        )
+    }
+
+    /// Append a tempo change in microseconds per quarter note
+    pub fn tempo(self, delta: u32, tempo: u32) -> Self {
+        self.event(delta, MidiMessage::Meta(MetaEvent::SetTempo(tempo)))
+    }
+
+    /// Finish the file, ending every track that does not already end with EndOfTrack.
+    ///
+    /// A single track produces a format-0 file; anything else is format 1. Fails with
+    /// more tracks than the header can count.
+    pub fn build(mut self) -> Result<MidiFile, MidiError> {
+        let num_tracks = track_count(self.tracks.len())?;
+        for track in &mut self.tracks {
+            if !track.ends_with_end_of_track() {
+                track.events.push(MidiEvent {
+                    delta_time: 0,
+                    message: MidiMessage::Meta(MetaEvent::EndOfTrack),
+                });
+            }
+        }
+
+        Ok(MidiFile {
+            header: MidiHeader {
+                format: if self.tracks.len() == 1 { 0 } else { 1 },
+                num_tracks,
+                time_division: self.time_division,
+            },
+            tracks: self.tracks,
+            chunks: Vec::new(),
+        })
+    }
+}
+
+impl MidiFile {
+    /// Build a file from per-track lists of (absolute tick, message), computing delta
+    /// times from tick 0.
+    ///
+    /// Each track is sorted by tick, keeping simultaneous events in the order given.
+    /// The header's track count is set to the number of tracks supplied. Fails if the
+    /// header's time division has zero ticks or there are more tracks than it can count.
+    pub fn from_absolute_events(
+        header: MidiHeader,
+        tracks: Vec<Vec<(u64, MidiMessage)>>,
+    ) -> Result<MidiFile, MidiError> {
+        if header.has_zero_division() {
+            return Err(MidiError::InvalidOperation(format!(
+                "Zero time division: 0x{:04X}",
+                header.time_division
+            )));
+        }
+        let num_tracks = track_count(tracks.len())?;
+        let tracks: Vec<MidiTrack> = tracks
+            .into_iter()
+            .map(|mut events| {
+                // Stable, so ties keep their insertion order
+                events.sort_by_key(|(tick, _)| *tick);
+                MidiTrack::from_absolute(events)
+            })
+            .collect();
+
+        Ok(MidiFile {
+            header: MidiHeader {
+                num_tracks,
+                ..header
+            },
+            tracks,
+            chunks: Vec::new(),
+        })
+    }
+}
+
+/// Number of tracks as stored in the header, which holds at most 65535
+fn track_count(tracks: usize) -> Result<u16, MidiError> {
+    u16::try_from(tracks)
+        .map_err(|_| MidiError::InvalidOperation(format!("Too many tracks: {}", tracks)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn header(time_division: u16) -> MidiHeader {
+        MidiHeader {
+            format: 1,
+            num_tracks: 0,
+            time_division,
+        }
+    }
+
+    fn note_on(note: u8) -> MidiMessage {
+        MidiMessage::NoteOn {
+            channel: Channel::new(0).unwrap(),
+            note,
+            velocity: 100,
+        }
+    }
+
+    fn event(delta_time: u32, message: MidiMessage) -> MidiEvent {
+        MidiEvent {
+            delta_time,
+            message,
+        }
+    }
+
+    #[test]
+    fn delta_times_count_from_tick_0() {
+        let end_of_track = MidiMessage::Meta(MetaEvent::EndOfTrack);
+        let file = MidiFile::from_absolute_events(
+            header(96),
+            vec![
+                vec![(0, MidiMessage::Meta(MetaEvent::SetTempo(500_000)))],
+                vec![
+                    (48, note_on(62)),
+                    (10, note_on(60)),
+                    (48, note_on(64)),
+                    (96, end_of_track.clone()),
+                ],
+            ],
+        )
+        .unwrap();
+        assert_eq!(file.header.num_tracks, 2);
+        assert_eq!(
+            file.tracks[1].events,
+            [
+                event(10, note_on(60)),
+                // Simultaneous events keep the order they were given in
+                event(38, note_on(62)),
+                event(0, note_on(64)),
+                event(48, end_of_track),
+            ]
+        );
+    }
+
+    #[test]
+    fn zero_time_division_is_rejected() {
+        for time_division in [0, 0xE700] {
+            assert!(matches!(
+                MidiFile::from_absolute_events(header(time_division), vec![vec![]]),
+                Err(MidiError::InvalidOperation(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use crate::MidiError;
+use alloc::format;
+use core::fmt;
+
+/// A MIDI channel, stored zero-based (0-15) and displayed one-based (1-16)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
+pub struct Channel(u8);
+
+impl Channel {
+    /// Channel with the given zero-based number, or None if it is not 0-15
+    pub const fn new(channel: u8) -> Option<Channel> {
+        if channel < 16 {
+            Some(Channel(channel))
+        } else {
+            None
+        }
+    }
+
+    /// Channel in the low nibble of a channel message status byte
+    pub const fn from_status(status: u8) -> Channel {
+        Channel(status & 0x0F)
+    }
+
+    /// Zero-based channel number, 0-15
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl fmt::Display for Channel {
+    /// The one-based channel number, as shown by most MIDI software
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0 + 1)
+    }
+}
+
+impl From<Channel> for u8 {
+    fn from(channel: Channel) -> u8 {
+        channel.0
+    }
+}
+
+impl TryFrom<u8> for Channel {
+    type Error = MidiError;
+
+    fn try_from(channel: u8) -> Result<Self, Self::Error> {
+        Channel::new(channel)
+            .ok_or_else(|| MidiError::InvalidOperation(format!("Invalid channel: {}", channel)))
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{MetaEvent, MidiError, MidiFile, MidiHeader, MidiMessage, MidiTrack};
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::iter;
+
+impl MidiFile {
+    /// Merge all tracks into a single format-0 track.
+    ///
+    /// Events are interleaved by absolute tick (ties keep track order) and a single
+    /// EndOfTrack is placed at the end of the longest source track.
+    pub fn to_format_0(&self) -> Result<MidiFile, MidiError> {
+        if self.header.format == 2 {
+            return Err(MidiError::InvalidOperation(
+                "Format 2 tracks are independent sequences and cannot be merged".to_string(),
+            ));
+        }
+
+        let end_tick = self
+            .tracks
+            .iter()
+            .filter_map(|track| track.absolute_times().last().copied())
+            .max()
+            .unwrap_or(0);
+
+        let merged = self
+            .merged_events()
+            .filter(|(_, _, event)| {
+                !matches!(event.message, MidiMessage::Meta(MetaEvent::EndOfTrack))
+            })
+            .map(|(tick, _, event)| (tick, event.message.clone()))
+            .chain(iter::once((
+                end_tick,
+                MidiMessage::Meta(MetaEvent::EndOfTrack),
+            )));
+
+        Ok(MidiFile {
+            header: MidiHeader {
+                format: 0,
+                num_tracks: 1,
+                time_division: self.header.time_division,
+            },
+            tracks: vec![MidiTrack::from_absolute(merged)],
+            chunks: self.chunks.clone(),
+        })
+    }
+
+    /// Split a single-track file into format 1 with one track per MIDI channel.
+    ///
+    /// Track 0 holds every event that has no channel (tempo, time signature and other
+    /// meta events, SysEx and system messages); it is followed by one track for each
+    /// channel in use, in ascending channel order. Each track ends with an EndOfTrack
+    /// at the end of the source track.
+    pub fn to_format_1(&self) -> Result<MidiFile, MidiError> {
+        let source = match self.tracks.as_slice() {
+            [track] => track,
+            _ => {
+                return Err(MidiError::InvalidOperation(format!(
+                    "Expected a single track to split, found {}",
+                    self.tracks.len()
+                )))
+            }
+        };
+
+        let mut conductor = Vec::new();
+        let mut channels: [Vec<(u64, MidiMessage)>; 16] = Default::default();
+        let mut end_tick = 0;
+        for (tick, event) in source.iter_absolute() {
+            end_tick = tick;
+            match event.message.channel() {
+                Some(channel) => {
+                    channels[channel.get() as usize].push((tick, event.message.clone()))
+                }
+                None if matches!(event.message, MidiMessage::Meta(MetaEvent::EndOfTrack)) => {}
+                None => conductor.push((tick, event.message.clone())),
+            }
+        }
+
+        let end_of_track = (end_tick, MidiMessage::Meta(MetaEvent::EndOfTrack));
+        let tracks: Vec<MidiTrack> = iter::once(conductor)
+            .chain(channels.into_iter().filter(|events| !events.is_empty()))
+            .map(|mut events| {
+                events.push(end_of_track.clone());
+                MidiTrack::from_absolute(events)
+            })
+            .collect();
+
+        Ok(MidiFile {
+            header: MidiHeader {
+                format: 1,
+                num_tracks: tracks.len() as u16,
+                time_division: self.header.time_division,
+            },
+            tracks,
+            chunks: self.chunks.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Channel;
+
+    fn note_on(channel: u8, note: u8) -> MidiMessage {
+        MidiMessage::NoteOn {
+            channel: Channel::new(channel).unwrap(),
+            note,
+            velocity: 100,
+        }
+    }
+
+    const END_OF_TRACK: MidiMessage = MidiMessage::Meta(MetaEvent::EndOfTrack);
+
+    fn format_1(tracks: Vec<Vec<(u64, MidiMessage)>>) -> MidiFile {
+        MidiFile {
+            header: MidiHeader {
+                format: 1,
+                num_tracks: tracks.len() as u16,
+                time_division: 96,
+            },
+            tracks: tracks.into_iter().map(MidiTrack::from_absolute).collect(),
+            chunks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn merged_track_interleaves_events_by_time() {
+        let file = format_1(vec![
+            vec![
+                (0, MidiMessage::Meta(MetaEvent::SetTempo(500_000))),
+                (50, END_OF_TRACK),
+            ],
+            vec![
+                (10, note_on(0, 60)),
+                (30, note_on(0, 62)),
+                (300, END_OF_TRACK),
+            ],
+            vec![(20, note_on(1, 40)), (200, END_OF_TRACK)],
+        ]);
+        let merged = file.to_format_0().unwrap();
+        assert_eq!(merged.header.format, 0);
+        assert_eq!(merged.header.num_tracks, 1);
+
+        let track = &merged.tracks[0];
+        let events: Vec<(u64, &MidiMessage)> = track
+            .iter_absolute()
+            .map(|(tick, event)| (tick, &event.message))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                (0, &MidiMessage::Meta(MetaEvent::SetTempo(500_000))),
+                (10, &note_on(0, 60)),
+                (20, &note_on(1, 40)),
+                (30, &note_on(0, 62)),
+                (300, &END_OF_TRACK),
+            ]
+        );
+        assert_eq!(merged.duration_ticks(), file.duration_ticks());
+    }
+
+    #[test]
+    fn format_2_cannot_be_merged() {
+        let mut file = format_1(vec![vec![(0, END_OF_TRACK)], vec![(0, END_OF_TRACK)]]);
+        file.header.format = 2;
+        assert!(matches!(
+            file.to_format_0(),
+            Err(MidiError::InvalidOperation(_))
+        ));
+    }
+}
@@ -0,0 +1,545 @@
+use crate::{
+    Channel, MetaEvent, MetaText, MidiError, MidiFile, MidiHeader, MidiMessage, MidiTrack,
+    TextEncoding,
+};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+/// One non-blank line of midicsv input, split into trimmed fields
+struct CsvLine {
+    number: usize, // 1-based line number
+    offset: u64,   // Byte offset of the line start
+    fields: Vec<String>,
+}
+
+impl CsvLine {
+    /// Format error naming this line
+    fn error(&self, message: impl std::fmt::Display) -> MidiError {
+        MidiError::Format {
+            message: format!("line {}: {}", self.number, message),
+            offset: self.offset,
+        }
+    }
+
+    /// Parse the field at `index` as a number
+    fn field<T: FromStr>(&self, index: usize) -> Result<T, MidiError> {
+        let text = self
+            .fields
+            .get(index)
+            .ok_or_else(|| self.error(format!("missing field {}", index + 1)))?;
+        text.parse()
+            .map_err(|_| self.error(format!("invalid number {:?}", text)))
+    }
+
+    /// Parse the field at `index` as a zero-based channel number
+    fn channel(&self, index: usize) -> Result<Channel, MidiError> {
+        let channel: u8 = self.field(index)?;
+        Channel::new(channel).ok_or_else(|| self.error(format!("invalid channel {}", channel)))
+    }
+
+    /// Unquote the text field at `index`, keeping the bytes as raw text if they are not
+    /// UTF-8
+    fn text(&self, index: usize) -> Result<MetaText, MidiError> {
+        let text = self
+            .fields
+            .get(index)
+            .ok_or_else(|| self.error(format!("missing field {}", index + 1)))?;
+        let inner = text
+            .strip_prefix('"')
+            .and_then(|t| t.strip_suffix('"'))
+            .ok_or_else(|| self.error(format!("expected quoted string, found {}", text)))?;
+
+        // Undo the doubled quotes and backslash escapes written by to_csv
+        let mut bytes = Vec::with_capacity(inner.len());
+        let mut chars = inner.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    bytes.push(b'"');
+                }
+                '\\' if chars.peek() == Some(&'\\') => {
+                    chars.next();
+                    bytes.push(b'\\');
+                }
+                '\\' => {
+                    let digits: String = (0..3).filter_map(|_| chars.next()).collect();
+                    let byte = u8::from_str_radix(&digits, 8)
+                        .map_err(|_| self.error(format!("invalid escape \\{}", digits)))?;
+                    bytes.push(byte);
+                }
+                c => {
+                    let mut utf8 = [0u8; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                }
+            }
+        }
+        Ok(match String::from_utf8(bytes) {
+            Ok(text) => MetaText::new(text),
+            Err(err) => MetaText::from_raw(err.into_bytes(), TextEncoding::default()),
+        })
+    }
+
+    /// Parse a `Length, Data...` byte list starting at `index`
+    fn bytes(&self, index: usize) -> Result<Vec<u8>, MidiError> {
+        let length: usize = self.field(index)?;
+        let data = (0..length)
+            .map(|i| self.field(index + 1 + i))
+            .collect::<Result<Vec<u8>, _>>()?;
+        if self.fields.len() != index + 1 + length {
+            return Err(self.error(format!(
+                "expected {} data bytes, found {}",
+                length,
+                self.fields.len().saturating_sub(index + 1)
+            )));
+        }
+        Ok(data)
+    }
+}
+
+impl MidiFile {
+    /// Write the file as text in the format used by the `midicsv` tool.
+    ///
+    /// Each line is `track, tick, record type, parameters...` with 1-based track numbers
+    /// and absolute ticks, framed by a `Header` line and an `End_of_file` line. Text
+    /// parameters are quoted with `"` doubled and control characters written as octal
+    /// `\ooo` escapes. Text read with `ParseOptions::keep_raw_text` is written as its
+    /// original bytes, escaping those above 127 too, so `from_csv` restores them exactly.
+    /// System Common and Real-Time messages have no midicsv record and produce an
+    /// `Encode` error.
+    pub fn to_csv<W: Write>(&self, w: &mut W) -> Result<(), MidiError> {
+        writeln!(
+            w,
+            "0, 0, Header, {}, {}, {}",
+            self.header.format,
+            self.tracks.len(),
+            self.header.time_division
+        )?;
+
+        for (index, track) in self.tracks.iter().enumerate() {
+            let track_number = index + 1;
+            writeln!(w, "{}, 0, Start_track", track_number)?;
+            for (tick, event) in track.iter_absolute() {
+                let record = Self::csv_record(&event.message)?;
+                writeln!(w, "{}, {}, {}", track_number, tick, record)?;
+            }
+        }
+
+        writeln!(w, "0, 0, End_of_file")?;
+        Ok(())
+    }
+
+    /// Record type and parameters for a single message
+    fn csv_record(message: &MidiMessage) -> Result<String, MidiError> {
+        let record = match message {
+            MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => format!("Note_off_c, {}, {}, {}", channel.get(), note, velocity),
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => format!("Note_on_c, {}, {}, {}", channel.get(), note, velocity),
+            MidiMessage::PolyphonicKeyPressure {
+                channel,
+                note,
+                pressure,
+            } => format!(
+                "Poly_aftertouch_c, {}, {}, {}",
+                channel.get(),
+                note,
+                pressure
+            ),
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => format!("Control_c, {}, {}, {}", channel.get(), controller, value),
+            MidiMessage::ProgramChange { channel, program } => {
+                format!("Program_c, {}, {}", channel.get(), program)
+            }
+            MidiMessage::ChannelPressure { channel, pressure } => {
+                format!("Channel_aftertouch_c, {}, {}", channel.get(), pressure)
+            }
+            // midicsv uses the raw unsigned 14-bit value
+            MidiMessage::PitchBendChange { channel, value } => {
+                format!("Pitch_bend_c, {}, {}", channel.get(), *value as i32 + 8192)
+            }
+            MidiMessage::SysEx(data) => format!("System_exclusive, {}", Self::csv_bytes(data)),
+            MidiMessage::SysExContinuation(data) => {
+                format!("System_exclusive_packet, {}", Self::csv_bytes(data))
+            }
+            MidiMessage::Meta(meta) => Self::csv_meta_record(meta),
+            MidiMessage::MtcQuarterFrame(_)
+            | MidiMessage::SongPositionPointer(_)
+            | MidiMessage::SongSelect(_)
+            | MidiMessage::TuneRequest
+            | MidiMessage::TimingClock
+            | MidiMessage::Start
+            | MidiMessage::Continue
+            | MidiMessage::Stop
+            | MidiMessage::ActiveSensing => {
+                return Err(MidiError::Encode(format!(
+                    "No midicsv record for system message {:?}",
+                    message
+                )))
+            }
+        };
+        Ok(record)
+    }
+
+    /// Record type and parameters for a meta event
+    fn csv_meta_record(meta: &MetaEvent) -> String {
+        match meta {
+            MetaEvent::SequenceNumber(number) => format!("Sequence_number, {}", number),
+            MetaEvent::Text(text) => format!("Text_t, {}", Self::csv_quote(text)),
+            MetaEvent::CopyrightNotice(text) => {
+                format!("Copyright_t, {}", Self::csv_quote(text))
+            }
+            MetaEvent::TrackName(text) => format!("Title_t, {}", Self::csv_quote(text)),
+            MetaEvent::InstrumentName(text) => {
+                format!("Instrument_name_t, {}", Self::csv_quote(text))
+            }
+            MetaEvent::Lyrics(text) => format!("Lyric_t, {}", Self::csv_quote(text)),
+            MetaEvent::Marker(text) => format!("Marker_t, {}", Self::csv_quote(text)),
+            MetaEvent::CuePoint(text) => format!("Cue_point_t, {}", Self::csv_quote(text)),
+            MetaEvent::ChannelPrefix(channel) => format!("Channel_prefix, {}", channel),
+            MetaEvent::MidiPort(port) => format!("MIDI_port, {}", port),
+            MetaEvent::EndOfTrack => "End_track".to_string(),
+            MetaEvent::SetTempo(tempo) => format!("Tempo, {}", tempo),
+            MetaEvent::SmpteOffset {
+                hours,
+                minutes,
+                seconds,
+                frames,
+                subframes,
+            } => format!(
+                "SMPTE_offset, {}, {}, {}, {}, {}",
+                hours, minutes, seconds, frames, subframes
+            ),
+            // midicsv writes the denominator as a power of two, as stored in the file
+            MetaEvent::TimeSignature {
+                numerator,
+                denominator,
+                clocks_per_metronome,
+                thirty_seconds_per_quarter,
+            } => format!(
+                "Time_signature, {}, {}, {}, {}",
+                numerator,
+                denominator.trailing_zeros(),
+                clocks_per_metronome,
+                thirty_seconds_per_quarter
+            ),
+            MetaEvent::KeySignature { key, scale } => {
+                let mode = if *scale == 0 { "major" } else { "minor" };
+                format!("Key_signature, {}, \"{}\"", key, mode)
+            }
+            MetaEvent::SequencerSpecific(data) => {
+                format!("Sequencer_specific, {}", Self::csv_bytes(data))
+            }
+        }
+    }
+
+    /// Length followed by each byte, comma separated
+    fn csv_bytes(data: &[u8]) -> String {
+        let mut fields = vec![data.len().to_string()];
+        fields.extend(data.iter().map(|byte| byte.to_string()));
+        fields.join(", ")
+    }
+
+    /// Quote a text parameter the way midicsv does. Raw bytes kept by the parser are
+    /// written instead of the decoded text, with bytes above 127 escaped like control
+    /// characters, so they survive a round trip through `from_csv`.
+    fn csv_quote(text: &MetaText) -> String {
+        let mut quoted = String::with_capacity(text.as_bytes().len() + 2);
+        quoted.push('"');
+        let escaped = |c: char| c.is_ascii_control() || (text.raw().is_some() && !c.is_ascii());
+        // Each raw byte becomes the char of the same value
+        let chars: Vec<char> = match text.raw() {
+            Some(raw) => raw.iter().map(|&byte| byte as char).collect(),
+            None => text.as_str().chars().collect(),
+        };
+        for c in chars {
+            match c {
+                '"' => quoted.push_str("\"\""),
+                '\\' => quoted.push_str("\\\\"),
+                c if escaped(c) => quoted.push_str(&format!("\\{:03o}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    /// Reconstruct a MIDI file from midicsv text as written by `to_csv`.
+    ///
+    /// Blank lines and lines starting with `#` are ignored. Malformed lines produce a
+    /// `Format` error naming the line number.
+    pub fn from_csv<R: Read>(mut r: R) -> Result<Self, MidiError> {
+        let mut input = Vec::new();
+        r.read_to_end(&mut input)?;
+        let input = String::from_utf8_lossy(&input);
+
+        let mut header = None;
+        let mut tracks: BTreeMap<usize, Vec<(u64, MidiMessage)>> = BTreeMap::new();
+        let mut offset = 0u64;
+        for (index, raw_line) in input.split('\n').enumerate() {
+            let line_offset = offset;
+            offset += raw_line.len() as u64 + 1;
+
+            let trimmed = raw_line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let line = CsvLine {
+                number: index + 1,
+                offset: line_offset,
+                fields: Self::csv_split(trimmed),
+            };
+
+            let track: usize = line.field(0)?;
+            let tick: u64 = line.field(1)?;
+            let record = line.fields.get(2).map(String::as_str).unwrap_or_default();
+            match record {
+                "Header" => {
+                    let parsed = MidiHeader {
+                        format: line.field(3)?,
+                        num_tracks: line.field(4)?,
+                        time_division: line.field(5)?,
+                    };
+                    if parsed.has_zero_division() {
+                        return Err(
+                            line.error(format!("zero time division {}", parsed.time_division))
+                        );
+                    }
+                    header = Some(parsed);
+                }
+                "Start_track" => {
+                    tracks.entry(track).or_default();
+                }
+                "End_of_file" => break,
+                _ => {
+                    let message = Self::csv_message(&line, record)?;
+                    tracks.entry(track).or_default().push((tick, message));
+                }
+            }
+        }
+
+        let header = header.ok_or_else(|| MidiError::Format {
+            message: "missing Header record".to_string(),
+            offset: 0,
+        })?;
+        let tracks = tracks
+            .into_values()
+            .map(|mut events| {
+                // Stable sort tolerates hand-edited input without reordering same-tick events
+                events.sort_by_key(|&(tick, _)| tick);
+                MidiTrack::from_absolute(events)
+            })
+            .collect();
+
+        Ok(MidiFile {
+            header,
+            tracks,
+            chunks: Vec::new(),
+        })
+    }
+
+    /// Decode the message for an event record
+    fn csv_message(line: &CsvLine, record: &str) -> Result<MidiMessage, MidiError> {
+        let message = match record {
+            "Note_off_c" => MidiMessage::NoteOff {
+                channel: line.channel(3)?,
+                note: line.field(4)?,
+                velocity: line.field(5)?,
+            },
+            "Note_on_c" => MidiMessage::NoteOn {
+                channel: line.channel(3)?,
+                note: line.field(4)?,
+                velocity: line.field(5)?,
+            },
+            "Poly_aftertouch_c" => MidiMessage::PolyphonicKeyPressure {
+                channel: line.channel(3)?,
+                note: line.field(4)?,
+                pressure: line.field(5)?,
+            },
+            "Control_c" => MidiMessage::ControlChange {
+                channel: line.channel(3)?,
+                controller: line.field(4)?,
+                value: line.field(5)?,
+            },
+            "Program_c" => MidiMessage::ProgramChange {
+                channel: line.channel(3)?,
+                program: line.field(4)?,
+            },
+            "Channel_aftertouch_c" => MidiMessage::ChannelPressure {
+                channel: line.channel(3)?,
+                pressure: line.field(4)?,
+            },
+            "Pitch_bend_c" => {
+                let raw: u16 = line.field(4)?;
+                if raw > 0x3FFF {
+                    return Err(line.error(format!("pitch bend out of range: {}", raw)));
+                }
+                MidiMessage::PitchBendChange {
+                    channel: line.channel(3)?,
+                    value: raw as i16 - 8192,
+                }
+            }
+            "System_exclusive" => MidiMessage::SysEx(line.bytes(3)?),
+            "System_exclusive_packet" => MidiMessage::SysExContinuation(line.bytes(3)?),
+            "Sequence_number" => MidiMessage::Meta(MetaEvent::SequenceNumber(line.field(3)?)),
+            "Text_t" => MidiMessage::Meta(MetaEvent::Text(line.text(3)?)),
+            "Copyright_t" => MidiMessage::Meta(MetaEvent::CopyrightNotice(line.text(3)?)),
+            "Title_t" => MidiMessage::Meta(MetaEvent::TrackName(line.text(3)?)),
+            "Instrument_name_t" => MidiMessage::Meta(MetaEvent::InstrumentName(line.text(3)?)),
+            "Lyric_t" => MidiMessage::Meta(MetaEvent::Lyrics(line.text(3)?)),
+            "Marker_t" => MidiMessage::Meta(MetaEvent::Marker(line.text(3)?)),
+            "Cue_point_t" => MidiMessage::Meta(MetaEvent::CuePoint(line.text(3)?)),
+            "Channel_prefix" => MidiMessage::Meta(MetaEvent::ChannelPrefix(line.channel(3)?.get())),
+            "MIDI_port" => MidiMessage::Meta(MetaEvent::MidiPort(line.field(3)?)),
+            "End_track" => MidiMessage::Meta(MetaEvent::EndOfTrack),
+            "Tempo" => MidiMessage::Meta(MetaEvent::SetTempo(line.field(3)?)),
+            "SMPTE_offset" => MidiMessage::Meta(MetaEvent::SmpteOffset {
+                hours: line.field(3)?,
+                minutes: line.field(4)?,
+                seconds: line.field(5)?,
+                frames: line.field(6)?,
+                subframes: line.field(7)?,
+            }),
+            "Time_signature" => {
+                let power: u8 = line.field(4)?;
+                if power > 7 {
+                    return Err(line.error(format!(
+                        "time signature denominator power too large: {}",
+                        power
+                    )));
+                }
+                MidiMessage::Meta(MetaEvent::TimeSignature {
+                    numerator: line.field(3)?,
+                    denominator: 1 << power,
+                    clocks_per_metronome: line.field(5)?,
+                    thirty_seconds_per_quarter: line.field(6)?,
+                })
+            }
+            "Key_signature" => {
+                let scale = match line.text(4)?.as_str() {
+                    "major" => 0,
+                    "minor" => 1,
+                    other => return Err(line.error(format!("invalid key mode {:?}", other))),
+                };
+                MidiMessage::Meta(MetaEvent::KeySignature {
+                    key: line.field(3)?,
+                    scale,
+                })
+            }
+            "Sequencer_specific" => MidiMessage::Meta(MetaEvent::SequencerSpecific(line.bytes(3)?)),
+            other => return Err(line.error(format!("unknown record type {:?}", other))),
+        };
+        Ok(message)
+    }
+
+    /// Split a line on commas that are not inside a quoted string
+    fn csv_split(line: &str) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut current = String::new();
+        let mut in_quotes = false;
+        for c in line.chars() {
+            match c {
+                '"' => {
+                    // A doubled quote toggles twice, staying inside the string
+                    in_quotes = !in_quotes;
+                    current.push(c);
+                }
+                ',' if !in_quotes => fields.push(std::mem::take(&mut current).trim().to_string()),
+                c => current.push(c),
+            }
+        }
+        fields.push(current.trim().to_string());
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A format-1 file at 480 ticks per quarter with the given track bodies, each of
+    /// which must end with an EndOfTrack
+    fn file_with_tracks(tracks: &[&[u8]]) -> MidiFile {
+        let mut data = b"MThd\0\0\0\x06\0\x01".to_vec();
+        data.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+        data.extend_from_slice(&480u16.to_be_bytes());
+        for events in tracks {
+            data.extend_from_slice(b"MTrk");
+            data.extend_from_slice(&(events.len() as u32).to_be_bytes());
+            data.extend_from_slice(events);
+        }
+        MidiFile::from_bytes(&data).unwrap()
+    }
+
+    fn csv(file: &MidiFile) -> String {
+        let mut out = Vec::new();
+        file.to_csv(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    const GOLDEN: &str = "\
+0, 0, Header, 1, 2, 480
+1, 0, Start_track
+1, 0, Title_t, \"Say \"\"hi\"\"\\011now\"
+1, 0, Time_signature, 3, 2, 24, 8
+1, 0, Key_signature, -2, \"minor\"
+1, 0, Tempo, 500000
+1, 960, End_track
+2, 0, Start_track
+2, 0, Program_c, 1, 5
+2, 0, Note_on_c, 1, 60, 100
+2, 120, Control_c, 1, 7, 90
+2, 240, Pitch_bend_c, 1, 8192
+2, 480, Note_off_c, 1, 60, 0
+2, 480, System_exclusive, 3, 126, 9, 247
+2, 960, End_track
+0, 0, End_of_file
+";
+
+    #[test]
+    fn csv_matches_midicsv_output() {
+        let mut conductor = vec![0x00, 0xFF, 0x03, 0x0C];
+        conductor.extend_from_slice(b"Say \"hi\"\tnow");
+        conductor.extend_from_slice(&[
+            0x00, 0xFF, 0x58, 0x04, 0x03, 0x02, 0x18, 0x08, // 3/4
+            0x00, 0xFF, 0x59, 0x02, 0xFE, 0x01, // G minor
+            0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // 120 BPM
+            0x87, 0x40, 0xFF, 0x2F, 0x00,
+        ]);
+        let notes: &[u8] = &[
+            0x00, 0xC1, 0x05, // Program 5
+            0x00, 0x91, 0x3C, 0x64, // NoteOn
+            0x78, 0xB1, 0x07, 0x5A, // Volume
+            0x78, 0xE1, 0x00, 0x40, // Pitch bend centre
+            0x81, 0x70, 0x81, 0x3C, 0x00, // NoteOff
+            0x00, 0xF0, 0x03, 0x7E, 0x09, 0xF7, // SysEx
+            0x83, 0x60, 0xFF, 0x2F, 0x00,
+        ];
+        assert_eq!(csv(&file_with_tracks(&[&conductor, notes])), GOLDEN);
+    }
+
+    #[test]
+    fn csv_reads_back_to_the_same_file() {
+        let file = MidiFile::from_csv(GOLDEN.as_bytes()).unwrap();
+        assert_eq!(csv(&file), GOLDEN);
+    }
+
+    #[test]
+    fn system_messages_have_no_record() {
+        let file = file_with_tracks(&[&[0x00, 0xF8, 0x00, 0xFF, 0x2F, 0x00]]);
+        assert!(matches!(
+            file.to_csv(&mut Vec::new()),
+            Err(MidiError::Encode(_))
+        ));
+    }
+}
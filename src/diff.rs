@@ -0,0 +1,189 @@
+use crate::{MidiEvent, MidiFile, MidiHeader, MidiTrack};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// How far ahead to look for a matching event when resynchronising after a mismatch
+const RESYNC_WINDOW: usize = 16;
+
+/// One way in which an actual MIDI file differs from the expected one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// The headers disagree in format, track count or time division
+    Header {
+        expected: MidiHeader,
+        actual: MidiHeader,
+    },
+    /// The files contain different numbers of tracks; extra tracks are not compared
+    TrackCount { expected: usize, actual: usize },
+    /// An event was replaced; `index` is its position in the expected track
+    Changed {
+        track: usize,
+        index: usize,
+        expected: MidiEvent,
+        actual: MidiEvent,
+    },
+    /// An expected event is missing; `index` is its position in the expected track
+    Removed {
+        track: usize,
+        index: usize,
+        expected: MidiEvent,
+    },
+    /// An event is not in the expected track; `index` is its position in the actual track
+    Added {
+        track: usize,
+        index: usize,
+        actual: MidiEvent,
+    },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difference::Header { expected, actual } => write!(
+                f,
+                "Header: expected format {} with {} tracks at division {}, found format {} with {} tracks at division {}",
+                expected.format,
+                expected.num_tracks,
+                expected.time_division,
+                actual.format,
+                actual.num_tracks,
+                actual.time_division
+            ),
+            Difference::TrackCount { expected, actual } => {
+                write!(f, "Expected {} tracks, found {}", expected, actual)
+            }
+            Difference::Changed {
+                track,
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Track {} event {}: expected +{} {}, found +{} {}",
+                track,
+                index,
+                expected.delta_time,
+                expected.message,
+                actual.delta_time,
+                actual.message
+            ),
+            Difference::Removed {
+                track,
+                index,
+                expected,
+            } => write!(
+                f,
+                "Track {} event {}: missing +{} {}",
+                track, index, expected.delta_time, expected.message
+            ),
+            Difference::Added {
+                track,
+                index,
+                actual,
+            } => write!(
+                f,
+                "Track {} event {}: unexpected +{} {}",
+                track, index, actual.delta_time, actual.message
+            ),
+        }
+    }
+}
+
+impl MidiFile {
+    /// Compare `other` against this file, treated as the expected one.
+    ///
+    /// Tracks are compared pairwise by index. Within a track, events are aligned so that
+    /// a few inserted or deleted events are reported as such instead of as every later
+    /// event differing. An empty result means the headers and tracks are equal.
+    pub fn diff(&self, other: &MidiFile) -> Vec<Difference> {
+        let mut differences = Vec::new();
+        if self.header != other.header {
+            differences.push(Difference::Header {
+                expected: self.header.clone(),
+                actual: other.header.clone(),
+            });
+        }
+        if self.tracks.len() != other.tracks.len() {
+            differences.push(Difference::TrackCount {
+                expected: self.tracks.len(),
+                actual: other.tracks.len(),
+            });
+        }
+
+        for (track, (expected, actual)) in self.tracks.iter().zip(&other.tracks).enumerate() {
+            diff_track(track, expected, actual, &mut differences);
+        }
+        differences
+    }
+}
+
+/// Greedily align two tracks' events, resynchronising within a small window after
+/// each mismatch
+fn diff_track(
+    track: usize,
+    expected: &MidiTrack,
+    actual: &MidiTrack,
+    differences: &mut Vec<Difference>,
+) {
+    let (a, b) = (&expected.events, &actual.events);
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        // Distance to the next occurrence of the other side's current event
+        let added = (1..=RESYNC_WINDOW).find(|&k| b.get(j + k) == Some(&a[i]));
+        let removed = (1..=RESYNC_WINDOW).find(|&k| a.get(i + k) == Some(&b[j]));
+        match (added, removed) {
+            (Some(k), r) if r.is_none_or(|r| k <= r) => {
+                for (index, event) in b.iter().enumerate().skip(j).take(k) {
+                    differences.push(Difference::Added {
+                        track,
+                        index,
+                        actual: event.clone(),
+                    });
+                }
+                j += k;
+            }
+            (_, Some(k)) => {
+                for (index, event) in a.iter().enumerate().skip(i).take(k) {
+                    differences.push(Difference::Removed {
+                        track,
+                        index,
+                        expected: event.clone(),
+                    });
+                }
+                i += k;
+            }
+            _ => {
+                differences.push(Difference::Changed {
+                    track,
+                    index: i,
+                    expected: a[i].clone(),
+                    actual: b[j].clone(),
+                });
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+
+    for (index, event) in a.iter().enumerate().skip(i) {
+        differences.push(Difference::Removed {
+            track,
+            index,
+            expected: event.clone(),
+        });
+    }
+    for (index, event) in b.iter().enumerate().skip(j) {
+        differences.push(Difference::Added {
+            track,
+            index,
+            actual: event.clone(),
+        });
+    }
+}
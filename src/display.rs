@@ -0,0 +1,139 @@
+use crate::pitch::note_name;
+use crate::{bpm_from_tempo, classify_sysex, key_signature_name, MetaEvent, MidiMessage};
+use core::fmt;
+
+impl fmt::Display for MidiMessage {
+    /// One-line summary with 1-based channel numbers, e.g. `NoteOn ch=1 note=C4 vel=100`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => write!(
+                f,
+                "NoteOn ch={} note={} vel={}",
+                channel,
+                note_name(*note),
+                velocity
+            ),
+            MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => write!(
+                f,
+                "NoteOff ch={} note={} vel={}",
+                channel,
+                note_name(*note),
+                velocity
+            ),
+            MidiMessage::PolyphonicKeyPressure {
+                channel,
+                note,
+                pressure,
+            } => write!(
+                f,
+                "PolyPressure ch={} note={} pressure={}",
+                channel,
+                note_name(*note),
+                pressure
+            ),
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => write!(
+                f,
+                "ControlChange ch={} cc={} value={}",
+                channel, controller, value
+            ),
+            MidiMessage::ProgramChange { channel, program } => {
+                write!(f, "ProgramChange ch={} program={}", channel, program)
+            }
+            MidiMessage::ChannelPressure { channel, pressure } => {
+                write!(f, "ChannelPressure ch={} pressure={}", channel, pressure)
+            }
+            MidiMessage::PitchBendChange { channel, value } => {
+                write!(f, "PitchBend ch={} value={}", channel, value)
+            }
+            MidiMessage::MtcQuarterFrame(value) => write!(f, "MtcQuarterFrame 0x{:02X}", value),
+            MidiMessage::SongPositionPointer(position) => {
+                write!(f, "SongPosition {}", position)
+            }
+            MidiMessage::SongSelect(song) => write!(f, "SongSelect {}", song),
+            MidiMessage::TuneRequest => f.write_str("TuneRequest"),
+            MidiMessage::TimingClock => f.write_str("TimingClock"),
+            MidiMessage::Start => f.write_str("Start"),
+            MidiMessage::Continue => f.write_str("Continue"),
+            MidiMessage::Stop => f.write_str("Stop"),
+            MidiMessage::ActiveSensing => f.write_str("ActiveSensing"),
+            MidiMessage::Meta(meta) => write!(f, "{}", meta),
+            MidiMessage::SysEx(data) => match classify_sysex(data) {
+                Some(kind) => write!(f, "SysEx len={} ({})", data.len(), kind.name()),
+                None => write!(f, "SysEx len={}", data.len()),
+            },
+            MidiMessage::SysExContinuation(data) => {
+                write!(f, "SysExContinuation len={}", data.len())
+            }
+        }
+    }
+}
+
+impl fmt::Display for MetaEvent {
+    /// One-line summary, e.g. `SetTempo 500000us (120.00 bpm)` or `TrackName "Piano"`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetaEvent::SequenceNumber(value) => write!(f, "SequenceNumber {}", value),
+            MetaEvent::Text(text) => write!(f, "Text {:?}", text.as_str()),
+            MetaEvent::CopyrightNotice(text) => write!(f, "Copyright {:?}", text.as_str()),
+            MetaEvent::TrackName(text) => write!(f, "TrackName {:?}", text.as_str()),
+            MetaEvent::InstrumentName(text) => write!(f, "InstrumentName {:?}", text.as_str()),
+            MetaEvent::Lyrics(text) => write!(f, "Lyrics {:?}", text.as_str()),
+            MetaEvent::Marker(text) => write!(f, "Marker {:?}", text.as_str()),
+            MetaEvent::CuePoint(text) => write!(f, "CuePoint {:?}", text.as_str()),
+            MetaEvent::ChannelPrefix(channel) => {
+                write!(f, "ChannelPrefix ch={}", u16::from(*channel) + 1)
+            }
+            MetaEvent::MidiPort(port) => write!(f, "MidiPort {}", port),
+            MetaEvent::EndOfTrack => f.write_str("EndOfTrack"),
+            MetaEvent::SetTempo(tempo) => {
+                write!(
+                    f,
+                    "SetTempo {}us ({:.2} bpm)",
+                    tempo,
+                    bpm_from_tempo(*tempo)
+                )
+            }
+            MetaEvent::SmpteOffset {
+                hours,
+                minutes,
+                seconds,
+                frames,
+                subframes,
+            } => write!(
+                f,
+                "SmpteOffset {:02}:{:02}:{:02}:{:02}.{:02}",
+                hours & 0x1F,
+                minutes,
+                seconds,
+                frames,
+                subframes
+            ),
+            MetaEvent::TimeSignature {
+                numerator,
+                denominator,
+                clocks_per_metronome,
+                thirty_seconds_per_quarter,
+            } => write!(
+                f,
+                "TimeSignature {}/{} clocks={} 32nds={}",
+                numerator, denominator, clocks_per_metronome, thirty_seconds_per_quarter
+            ),
+            MetaEvent::KeySignature { key, scale } => {
+                write!(f, "KeySignature {}", key_signature_name(*key, *scale))
+            }
+            MetaEvent::SequencerSpecific(data) => write!(f, "SequencerSpecific len={}", data.len()),
+        }
+    }
+}
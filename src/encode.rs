@@ -0,0 +1,403 @@
+use crate::{MetaEvent, MidiError, MidiMessage};
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Largest value representable as a 4-byte variable-length quantity
+const MAX_VARIABLE_LENGTH: u32 = 0x0FFF_FFFF;
+
+impl MidiMessage {
+    /// Status byte that begins the message, or None for meta events, which only exist
+    /// in files and have no status of their own on the wire
+    pub fn status_byte(&self) -> Option<u8> {
+        let status = match *self {
+            MidiMessage::NoteOff { channel, .. } => 0x80 | channel.get(),
+            MidiMessage::NoteOn { channel, .. } => 0x90 | channel.get(),
+            MidiMessage::PolyphonicKeyPressure { channel, .. } => 0xA0 | channel.get(),
+            MidiMessage::ControlChange { channel, .. } => 0xB0 | channel.get(),
+            MidiMessage::ProgramChange { channel, .. } => 0xC0 | channel.get(),
+            MidiMessage::ChannelPressure { channel, .. } => 0xD0 | channel.get(),
+            MidiMessage::PitchBendChange { channel, .. } => 0xE0 | channel.get(),
+            MidiMessage::SysEx(_) => 0xF0,
+            MidiMessage::MtcQuarterFrame(_) => 0xF1,
+            MidiMessage::SongPositionPointer(_) => 0xF2,
+            MidiMessage::SongSelect(_) => 0xF3,
+            MidiMessage::TuneRequest => 0xF6,
+            MidiMessage::SysExContinuation(_) => 0xF7,
+            MidiMessage::TimingClock => 0xF8,
+            MidiMessage::Start => 0xFA,
+            MidiMessage::Continue => 0xFB,
+            MidiMessage::Stop => 0xFC,
+            MidiMessage::ActiveSensing => 0xFE,
+            MidiMessage::Meta(_) => return None,
+        };
+        Some(status)
+    }
+
+    /// The message as sent over a MIDI connection, without a delta time.
+    ///
+    /// SysEx is framed by its 0xF0 status instead of a length and a SysEx continuation
+    /// sends its bytes as they are. Fails for meta events, which only exist in files,
+    /// and for a value that cannot be represented, as `encode` does. Use
+    /// `TryFrom<&[u8]>` to decode the result.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MidiError> {
+        match self {
+            MidiMessage::SysEx(data) => {
+                let mut bytes = Vec::with_capacity(data.len() + 1);
+                bytes.push(0xF0);
+                bytes.extend_from_slice(data);
+                Ok(bytes)
+            }
+            MidiMessage::SysExContinuation(data) => Ok(data.clone()),
+            MidiMessage::Meta(_) => Err(MidiError::Encode(
+                "Meta events have no wire form".to_string(),
+            )),
+            _ => {
+                let mut bytes = Vec::with_capacity(3);
+                self.encode(&mut bytes)?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    /// Append the message's bytes as stored in a file: the status byte (always, without
+    /// running status) followed by its data, or the length-prefixed form for SysEx and
+    /// meta events.
+    ///
+    /// Fails if a value cannot be represented, such as a data byte above 127 or a tempo
+    /// wider than 24 bits.
+    pub fn encode(&self, buf: &mut Vec<u8>) -> Result<(), MidiError> {
+        match self {
+            MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => buf.extend_from_slice(&[
+                0x80 | channel.get(),
+                data_byte("note", *note)?,
+                data_byte("velocity", *velocity)?,
+            ]),
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => buf.extend_from_slice(&[
+                0x90 | channel.get(),
+                data_byte("note", *note)?,
+                data_byte("velocity", *velocity)?,
+            ]),
+            MidiMessage::PolyphonicKeyPressure {
+                channel,
+                note,
+                pressure,
+            } => buf.extend_from_slice(&[
+                0xA0 | channel.get(),
+                data_byte("note", *note)?,
+                data_byte("pressure", *pressure)?,
+            ]),
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => buf.extend_from_slice(&[
+                0xB0 | channel.get(),
+                data_byte("controller", *controller)?,
+                data_byte("value", *value)?,
+            ]),
+            MidiMessage::ProgramChange { channel, program } => {
+                buf.extend_from_slice(&[0xC0 | channel.get(), data_byte("program", *program)?])
+            }
+            MidiMessage::ChannelPressure { channel, pressure } => {
+                buf.extend_from_slice(&[0xD0 | channel.get(), data_byte("pressure", *pressure)?])
+            }
+            MidiMessage::PitchBendChange { channel, value } => {
+                if !(-8192..=8191).contains(value) {
+                    return Err(MidiError::Encode(format!(
+                        "Pitch bend out of range: {}",
+                        value
+                    )));
+                }
+                // Undo the centering applied when parsing
+                let raw = (*value + 8192) as u16;
+                let lsb = (raw & 0x7F) as u8;
+                let msb = (raw >> 7) as u8;
+                buf.extend_from_slice(&[0xE0 | channel.get(), lsb, msb]);
+            }
+            MidiMessage::MtcQuarterFrame(value) => {
+                buf.extend_from_slice(&[0xF1, data_byte("MTC quarter frame", *value)?])
+            }
+            MidiMessage::SongPositionPointer(position) => {
+                if *position > 0x3FFF {
+                    return Err(MidiError::Encode(format!(
+                        "Song position does not fit in 14 bits: {}",
+                        position
+                    )));
+                }
+                let lsb = (position & 0x7F) as u8;
+                let msb = (position >> 7) as u8;
+                buf.extend_from_slice(&[0xF2, lsb, msb]);
+            }
+            MidiMessage::SongSelect(song) => {
+                buf.extend_from_slice(&[0xF3, data_byte("song", *song)?])
+            }
+            MidiMessage::TuneRequest => buf.push(0xF6),
+            MidiMessage::TimingClock => buf.push(0xF8),
+            MidiMessage::Start => buf.push(0xFA),
+            MidiMessage::Continue => buf.push(0xFB),
+            MidiMessage::Stop => buf.push(0xFC),
+            MidiMessage::ActiveSensing => buf.push(0xFE),
+            MidiMessage::SysEx(data) => encode_sysex(0xF0, data, buf)?,
+            MidiMessage::SysExContinuation(data) => encode_sysex(0xF7, data, buf)?,
+            MidiMessage::Meta(meta) => encode_meta(meta, buf)?,
+        }
+        Ok(())
+    }
+}
+
+/// Check that a channel or system message value fits in a 7-bit data byte
+fn data_byte(name: &str, value: u8) -> Result<u8, MidiError> {
+    if value > 0x7F {
+        return Err(MidiError::Encode(format!(
+            "Data byte out of range: {} {}",
+            name, value
+        )));
+    }
+    Ok(value)
+}
+
+/// Encode a length-prefixed SysEx packet
+fn encode_sysex(status: u8, data: &[u8], buf: &mut Vec<u8>) -> Result<(), MidiError> {
+    let length = u32::try_from(data.len())
+        .map_err(|_| MidiError::Encode(format!("SysEx too long: {} bytes", data.len())))?;
+
+    buf.push(status);
+    write_variable_length(length, buf)?;
+    buf.extend_from_slice(data);
+    Ok(())
+}
+
+/// Encode a meta event (0xFF, type, length, data)
+fn encode_meta(meta: &MetaEvent, buf: &mut Vec<u8>) -> Result<(), MidiError> {
+    let (meta_type, data): (u8, Vec<u8>) = match meta {
+        MetaEvent::SequenceNumber(value) => (0x00, value.to_be_bytes().to_vec()),
+        MetaEvent::Text(text) => (0x01, text.as_bytes().to_vec()),
+        MetaEvent::CopyrightNotice(text) => (0x02, text.as_bytes().to_vec()),
+        MetaEvent::TrackName(text) => (0x03, text.as_bytes().to_vec()),
+        MetaEvent::InstrumentName(text) => (0x04, text.as_bytes().to_vec()),
+        MetaEvent::Lyrics(text) => (0x05, text.as_bytes().to_vec()),
+        MetaEvent::Marker(text) => (0x06, text.as_bytes().to_vec()),
+        MetaEvent::CuePoint(text) => (0x07, text.as_bytes().to_vec()),
+        MetaEvent::ChannelPrefix(channel) => {
+            if *channel > 15 {
+                return Err(MidiError::Encode(format!(
+                    "Channel prefix out of range: {}",
+                    channel
+                )));
+            }
+            (0x20, vec![*channel])
+        }
+        MetaEvent::MidiPort(port) => (0x21, vec![*port]),
+        MetaEvent::EndOfTrack => (0x2F, Vec::new()),
+        MetaEvent::SetTempo(tempo) => {
+            if *tempo > 0xFF_FFFF {
+                return Err(MidiError::Encode(format!(
+                    "Tempo does not fit in 24 bits: {}",
+                    tempo
+                )));
+            }
+            (0x51, tempo.to_be_bytes()[1..].to_vec())
+        }
+        MetaEvent::SmpteOffset {
+            hours,
+            minutes,
+            seconds,
+            frames,
+            subframes,
+        } => (0x54, vec![*hours, *minutes, *seconds, *frames, *subframes]),
+        MetaEvent::TimeSignature {
+            numerator,
+            denominator,
+            clocks_per_metronome,
+            thirty_seconds_per_quarter,
+        } => {
+            if !denominator.is_power_of_two() {
+                return Err(MidiError::Encode(format!(
+                    "Time signature denominator is not a power of two: {}",
+                    denominator
+                )));
+            }
+            let denominator_power = denominator.trailing_zeros() as u8; // log2
+            (
+                0x58,
+                vec![
+                    *numerator,
+                    denominator_power,
+                    *clocks_per_metronome,
+                    *thirty_seconds_per_quarter,
+                ],
+            )
+        }
+        MetaEvent::KeySignature { key, scale } => (0x59, vec![*key as u8, *scale]),
+        MetaEvent::SequencerSpecific(data) => (0x7F, data.clone()),
+    };
+
+    let length = u32::try_from(data.len())
+        .map_err(|_| MidiError::Encode(format!("Meta event too long: {} bytes", data.len())))?;
+
+    buf.push(0xFF);
+    buf.push(meta_type);
+    write_variable_length(length, buf)?;
+    buf.extend_from_slice(&data);
+    Ok(())
+}
+
+/// Write a variable-length quantity
+pub(crate) fn write_variable_length(value: u32, buf: &mut Vec<u8>) -> Result<(), MidiError> {
+    if value > MAX_VARIABLE_LENGTH {
+        return Err(MidiError::Encode(format!(
+            "Value too large for variable-length quantity: {}",
+            value
+        )));
+    }
+
+    // Collect 7-bit groups, least significant first
+    let mut groups = [0u8; 4];
+    let mut count = 0;
+    let mut remaining = value;
+    loop {
+        groups[count] = (remaining & 0x7F) as u8;
+        count += 1;
+        remaining >>= 7;
+        if remaining == 0 {
+            break;
+        }
+    }
+
+    // Emit most significant first, with the continuation bit on all but the last
+    for i in (0..count).rev() {
+        let byte = if i > 0 { groups[i] | 0x80 } else { groups[i] };
+        buf.push(byte);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Channel;
+
+    /// Every channel message on every channel, with data values covering the edges
+    fn channel_messages() -> Vec<MidiMessage> {
+        let mut messages = Vec::new();
+        for channel in (0..16).filter_map(Channel::new) {
+            for data in [0, 1, 64, 126, 127] {
+                messages.extend([
+                    MidiMessage::NoteOff {
+                        channel,
+                        note: data,
+                        velocity: 127 - data,
+                    },
+                    MidiMessage::NoteOn {
+                        channel,
+                        note: 127 - data,
+                        velocity: data,
+                    },
+                    MidiMessage::PolyphonicKeyPressure {
+                        channel,
+                        note: data,
+                        pressure: data,
+                    },
+                    MidiMessage::ControlChange {
+                        channel,
+                        controller: data,
+                        value: 127 - data,
+                    },
+                    MidiMessage::ProgramChange {
+                        channel,
+                        program: data,
+                    },
+                    MidiMessage::ChannelPressure {
+                        channel,
+                        pressure: data,
+                    },
+                ]);
+            }
+            for value in [-8192, -8191, -1, 0, 1, 8190, 8191] {
+                messages.push(MidiMessage::PitchBendChange { channel, value });
+            }
+        }
+        messages
+    }
+
+    #[test]
+    fn to_bytes_round_trips_channel_messages() {
+        for message in channel_messages() {
+            let bytes = message.to_bytes().unwrap();
+            assert_eq!(MidiMessage::try_from(&bytes[..]).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn to_bytes_keeps_note_on_with_velocity_zero() {
+        let message = MidiMessage::NoteOn {
+            channel: Channel::new(9).unwrap(),
+            note: 38,
+            velocity: 0,
+        };
+        let bytes = message.to_bytes().unwrap();
+        assert_eq!(bytes, [0x99, 38, 0]);
+        assert_eq!(MidiMessage::try_from(&bytes[..]).unwrap(), message);
+    }
+
+    #[test]
+    fn pitch_bend_splits_into_lsb_and_msb() {
+        let channel = Channel::new(0).unwrap();
+        let bytes = |value| {
+            MidiMessage::PitchBendChange { channel, value }
+                .to_bytes()
+                .unwrap()
+        };
+        assert_eq!(bytes(0), [0xE0, 0x00, 0x40]);
+        assert_eq!(bytes(-8192), [0xE0, 0x00, 0x00]);
+        assert_eq!(bytes(8191), [0xE0, 0x7F, 0x7F]);
+    }
+
+    #[test]
+    fn to_bytes_round_trips_system_messages() {
+        let messages = [
+            MidiMessage::SysEx(vec![0x7E, 0x7F, 0x09, 0x01, 0xF7]),
+            MidiMessage::MtcQuarterFrame(0x35),
+            MidiMessage::SongPositionPointer(0x3FFF),
+            MidiMessage::SongSelect(7),
+            MidiMessage::TuneRequest,
+            MidiMessage::TimingClock,
+            MidiMessage::Start,
+            MidiMessage::Continue,
+            MidiMessage::Stop,
+            MidiMessage::ActiveSensing,
+        ];
+        for message in messages {
+            let bytes = message.to_bytes().unwrap();
+            assert_eq!(MidiMessage::try_from(&bytes[..]).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn to_bytes_rejects_meta_events_and_wide_values() {
+        assert!(MidiMessage::Meta(MetaEvent::EndOfTrack).to_bytes().is_err());
+        let channel = Channel::new(0).unwrap();
+        let note_on = MidiMessage::NoteOn {
+            channel,
+            note: 200,
+            velocity: 64,
+        };
+        assert!(note_on.to_bytes().is_err());
+        assert!(MidiMessage::PitchBendChange {
+            channel,
+            value: 8192
+        }
+        .to_bytes()
+        .is_err());
+    }
+}
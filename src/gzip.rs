@@ -0,0 +1,31 @@
+use crate::MidiError;
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+/// First two bytes of every gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// Decompress the file if it starts with the gzip magic number, reading at most `limit`
+/// decompressed bytes. Returns None, with the file rewound, if it is not gzip data.
+pub(crate) fn decompress_if_gzip(
+    file: &mut File,
+    limit: u64,
+) -> Result<Option<Vec<u8>>, MidiError> {
+    let mut magic = [0u8; 2];
+    let is_gzip = match file.read_exact(&mut magic) {
+        Ok(()) => magic == GZIP_MAGIC,
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => false,
+        Err(err) => return Err(err.into()),
+    };
+    file.seek(SeekFrom::Start(0))?;
+    if !is_gzip {
+        return Ok(None);
+    }
+
+    let mut data = Vec::new();
+    MultiGzDecoder::new(file)
+        .take(limit)
+        .read_to_end(&mut data)?;
+    Ok(Some(data))
+}
@@ -0,0 +1,69 @@
+use crate::{MetaEvent, MidiFile, MidiMessage};
+use alloc::vec::Vec;
+
+/// FNV-1a 64-bit offset basis and prime
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// Incremental FNV-1a hash, which is fixed by its definition on every platform
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+impl MidiFile {
+    /// Deterministic 64-bit FNV-1a hash of the file's content, for spotting duplicates.
+    ///
+    /// The hashed bytes are the header format and time division (each u16 big-endian),
+    /// then for each track the tag `MTrk` followed, for every event, by its absolute tick
+    /// (u64 big-endian) and its message as encoded in a file with an explicit status byte.
+    /// Hashing parsed events means running status and other encoding choices never matter.
+    ///
+    /// With `musical_only`, the format is left out and text meta events (0x01-0x07, such
+    /// as track names, lyrics and markers) are skipped, so files differing only in those
+    /// hash equally. Absolute ticks keep the remaining events aligned either way.
+    pub fn content_hash(&self, musical_only: bool) -> u64 {
+        let mut hash = Fnv1a(FNV_OFFSET);
+        if !musical_only {
+            hash.write(&self.header.format.to_be_bytes());
+        }
+        hash.write(&self.header.time_division.to_be_bytes());
+
+        let mut buf = Vec::new();
+        for track in &self.tracks {
+            hash.write(b"MTrk");
+            for (tick, event) in track.iter_absolute() {
+                if musical_only && is_text(&event.message) {
+                    continue;
+                }
+                hash.write(&tick.to_be_bytes());
+                buf.clear();
+                // A value too wide to encode contributes whatever was written before it
+                let _ = event.message.encode(&mut buf);
+                hash.write(&buf);
+            }
+        }
+        hash.0
+    }
+}
+
+/// True for the text meta events, types 0x01-0x07
+fn is_text(message: &MidiMessage) -> bool {
+    matches!(
+        message,
+        MidiMessage::Meta(
+            MetaEvent::Text(_)
+                | MetaEvent::CopyrightNotice(_)
+                | MetaEvent::TrackName(_)
+                | MetaEvent::InstrumentName(_)
+                | MetaEvent::Lyrics(_)
+                | MetaEvent::Marker(_)
+                | MetaEvent::CuePoint(_)
+        )
+    )
+}
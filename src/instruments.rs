@@ -0,0 +1,75 @@
+use crate::{gm_program_name, Channel, MidiFile, MidiMessage, MidiTrack, GM_DRUM_CHANNEL};
+use alloc::vec::Vec;
+
+/// Controller numbers for the two halves of the bank number
+const BANK_SELECT_MSB: u8 = 0;
+const BANK_SELECT_LSB: u8 = 32;
+
+impl MidiTrack {
+    /// Every ProgramChange with the bank in force on its channel at that moment,
+    /// as (tick, channel, bank, program).
+    ///
+    /// The 14-bit bank is `(msb << 7) | lsb` from the most recent Bank Select
+    /// controllers (CC 0 and CC 32) on the same channel; both start at 0.
+    pub fn program_changes_with_bank(&self) -> Vec<(u64, Channel, u16, u8)> {
+        let mut banks = [(0u8, 0u8); 16];
+        let mut changes = Vec::new();
+
+        for (tick, event) in self.iter_absolute() {
+            match event.message {
+                MidiMessage::ControlChange {
+                    channel,
+                    controller,
+                    value,
+                } => {
+                    let bank = &mut banks[channel.get() as usize];
+                    match controller {
+                        BANK_SELECT_MSB => bank.0 = value,
+                        BANK_SELECT_LSB => bank.1 = value,
+                        _ => {}
+                    }
+                }
+                MidiMessage::ProgramChange { channel, program } => {
+                    let (msb, lsb) = banks[channel.get() as usize];
+                    let bank = ((msb as u16) << 7) | lsb as u16;
+                    changes.push((tick, channel, bank, program));
+                }
+                _ => {}
+            }
+        }
+
+        changes
+    }
+}
+
+impl MidiFile {
+    /// Distinct (channel, program) pairs set by ProgramChange events, in the order they
+    /// first occur on the file's timeline
+    pub fn instruments(&self) -> Vec<(Channel, u8)> {
+        let mut instruments = Vec::new();
+        for (_, _, event) in self.merged_events() {
+            if let MidiMessage::ProgramChange { channel, program } = event.message {
+                if !instruments.contains(&(channel, program)) {
+                    instruments.push((channel, program));
+                }
+            }
+        }
+        instruments
+    }
+
+    /// `instruments` with a General MIDI name for each pair. Channel 10 is named
+    /// "Percussion" whatever its program, since GM reserves it for drum kits.
+    pub fn instrument_names(&self) -> Vec<(Channel, u8, &'static str)> {
+        self.instruments()
+            .into_iter()
+            .map(|(channel, program)| {
+                let name = if channel == GM_DRUM_CHANNEL {
+                    "Percussion"
+                } else {
+                    gm_program_name(program)
+                };
+                (channel, program, name)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,30 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+
+/// Major key tonics from seven flats (index 0) to seven sharps (index 14)
+const MAJOR_KEYS: [&str; 15] = [
+    "Cb", "Gb", "Db", "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#",
+];
+
+/// Minor key tonics from seven flats (index 0) to seven sharps (index 14)
+const MINOR_KEYS: [&str; 15] = [
+    "Ab", "Eb", "Bb", "F", "C", "G", "D", "A", "E", "B", "F#", "C#", "G#", "D#", "A#",
+];
+
+/// Name of the key described by a KeySignature meta event, e.g. "Eb major" or "B minor".
+///
+/// `key` is the number of sharps (positive) or flats (negative) and `scale` is 0 for
+/// major or 1 for minor. Values outside those ranges produce "invalid".
+pub fn key_signature_name(key: i8, scale: u8) -> String {
+    let tonics = match scale {
+        0 => &MAJOR_KEYS,
+        1 => &MINOR_KEYS,
+        _ => return "invalid".to_string(),
+    };
+    if !(-7..=7).contains(&key) {
+        return "invalid".to_string();
+    }
+
+    let mode = if scale == 0 { "major" } else { "minor" };
+    format!("{} {}", tonics[(key + 7) as usize], mode)
+}
@@ -0,0 +1,1683 @@
+//! Parsing, editing and writing of Standard MIDI Files.
+//!
+//! Start with `MidiFile::open` or `MidiFile::from_bytes`; the `midi_oxide` binary is a
+//! thin command-line front end over this library.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+#[cfg(feature = "std")]
+use source::BufferedSource;
+use source::{MidiSource, SliceReader};
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{self, Read, Seek};
+#[cfg(feature = "std")]
+use std::path::Path;
+use stream::TrackState;
+use thiserror::Error;
+
+mod bend;
+mod builder;
+mod channel;
+mod convert;
+#[cfg(feature = "std")]
+mod csv;
+mod diff;
+mod display;
+mod encode;
+mod gm;
+#[cfg(feature = "gzip")]
+mod gzip;
+mod hash;
+mod instruments;
+mod key_signature;
+mod lyrics;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod notes;
+mod pitch;
+#[cfg(feature = "playback")]
+mod playback;
+pub mod prelude;
+mod rpn;
+mod source;
+mod stats;
+mod stream;
+#[cfg(feature = "std")]
+mod summary;
+mod sysex;
+mod text;
+mod timing;
+mod transform;
+mod validate;
+mod warning;
+#[cfg(feature = "std")]
+mod writer;
+
+pub use builder::MidiBuilder;
+pub use channel::Channel;
+pub use diff::Difference;
+pub use gm::{gm_drum_name, gm_program_name, GM_DRUM_CHANNEL};
+pub use key_signature::key_signature_name;
+pub use notes::Note;
+pub use pitch::{
+    note_from_name, note_from_name_with_middle_c, note_name, note_name_with_middle_c,
+    MIDDLE_C_OCTAVE,
+};
+#[cfg(feature = "playback")]
+pub use playback::Playback;
+pub use rpn::{RpnDecoder, RpnEvent};
+pub use stats::{RunningStatusStats, TrackStats};
+#[cfg(feature = "std")]
+pub use stream::TrackParser;
+#[cfg(feature = "std")]
+pub use summary::FileSummary;
+pub use sysex::{classify_sysex, sysex_manufacturer, ManufacturerId, SysExKind};
+pub use text::{MetaText, TextEncoding};
+pub use timing::{
+    bpm_from_tempo, tempo_from_bpm, TempoMap, TimeSignatureMap, DEFAULT_TEMPO,
+    DEFAULT_TIME_DIVISION, DEFAULT_TIME_SIGNATURE,
+};
+pub use transform::SIGNIFICANT_CC_CHANGE;
+pub use validate::ValidationWarning;
+pub use warning::ParseWarning;
+#[cfg(feature = "std")]
+pub use writer::WriteOptions;
+
+/// Represents a complete MIDI file
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MidiFile {
+    pub header: MidiHeader,
+    pub tracks: Vec<MidiTrack>,
+    pub chunks: Vec<RawChunk>, // Unrecognized chunks in file order, written before the tracks
+}
+
+/// A chunk with an unknown type, such as a vendor extension, kept as raw bytes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawChunk {
+    pub id: [u8; 4], // Chunk type, e.g. b"XFIH"
+    pub data: Vec<u8>,
+}
+
+/// Result of a lenient parse: the tracks that could be read plus errors for the rest
+#[derive(Debug)]
+pub struct LenientParse {
+    pub file: MidiFile, // Successfully parsed tracks, in file order
+    pub track_errors: Vec<(usize, MidiError)>, // Index of each skipped track chunk and why
+    pub warnings: Vec<ParseWarning>, // Irregularities that were worked around
+    pub running_status: Vec<RunningStatusStats>, // Status byte counts for each track in `file`
+}
+
+/// Result of parsing a possibly truncated file: everything read before the input ran out
+#[derive(Debug)]
+pub struct PartialParse {
+    pub file: MidiFile,  // Complete events read so far; the header is kept as declared
+    pub truncated: bool, // The input ended before the last declared track was complete
+}
+
+/// Choices that affect how MIDI data is parsed
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Reject data bytes with the high bit set (a Format error) instead of masking the
+    /// bit off. Off by default, so corrupt values are clamped into 0-127 and parsing goes on.
+    pub strict_data_bytes: bool,
+    /// Reject files whose header declares more tracks than this (a Format error).
+    /// Unlimited by default.
+    pub max_tracks: usize,
+    /// Reject inputs longer than this many bytes (a Format error). Unlimited by default.
+    pub max_file_bytes: u64,
+    /// Keep a NoteOn with velocity 0 as a NoteOn instead of turning it into a NoteOff, so
+    /// the file can be written back byte for byte. Off by default.
+    pub preserve_note_on_zero: bool,
+    /// How the bytes of text meta events (track names, lyrics and so on) are decoded.
+    /// UTF-8 with invalid sequences replaced by default.
+    pub text_encoding: TextEncoding,
+    /// Keep the original bytes of text meta events next to the decoded text, so they are
+    /// written back unchanged whatever the encoding. Off by default.
+    pub keep_raw_text: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            strict_data_bytes: false,
+            max_tracks: usize::MAX,
+            max_file_bytes: u64::MAX,
+            preserve_note_on_zero: false,
+            text_encoding: TextEncoding::default(),
+            keep_raw_text: false,
+        }
+    }
+}
+
+/// MIDI file header information
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MidiHeader {
+    pub format: u16,        // 0: single track, 1: multiple tracks, 2: multiple songs
+    pub num_tracks: u16,    // Number of track chunks
+    pub time_division: u16, // Timing information (ticks per quarter note or SMPTE format)
+}
+
+/// Decoded form of the header's time division word
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeDivision {
+    TicksPerQuarter(u16),
+    Smpte {
+        fps: u8,             // Frames per second (24, 25, 29 for 29.97 drop-frame, or 30)
+        ticks_per_frame: u8, // Subdivisions of each frame
+    },
+}
+
+impl MidiHeader {
+    /// Decode the raw time division into ticks-per-quarter or SMPTE timing
+    pub fn time_division_parsed(&self) -> TimeDivision {
+        if self.time_division & 0x8000 == 0 {
+            TimeDivision::TicksPerQuarter(self.time_division)
+        } else {
+            // The high byte holds the frame rate as a negative two's complement value
+            let [frames, ticks_per_frame] = self.time_division.to_be_bytes();
+            TimeDivision::Smpte {
+                fps: (frames as i8).unsigned_abs(),
+                ticks_per_frame,
+            }
+        }
+    }
+
+    /// True if there are zero ticks per beat or per frame, which would divide by zero in
+    /// every time conversion
+    pub(crate) fn has_zero_division(&self) -> bool {
+        match self.time_division_parsed() {
+            TimeDivision::TicksPerQuarter(ticks) => ticks == 0,
+            TimeDivision::Smpte {
+                fps,
+                ticks_per_frame,
+            } => fps == 0 || ticks_per_frame == 0,
+        }
+    }
+}
+
+/// A single MIDI track containing events
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MidiTrack {
+    pub events: Vec<MidiEvent>,
+}
+
+/// A MIDI event with timing information
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MidiEvent {
+    pub delta_time: u32, // Time in ticks since previous event
+    pub message: MidiMessage,
+}
+
+/// Different types of MIDI messages
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MidiMessage {
+    NoteOn {
+        channel: Channel,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOff {
+        channel: Channel,
+        note: u8,
+        velocity: u8,
+    },
+    PolyphonicKeyPressure {
+        channel: Channel,
+        note: u8,
+        pressure: u8,
+    },
+    ControlChange {
+        channel: Channel,
+        controller: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: Channel,
+        program: u8,
+    },
+    ChannelPressure {
+        channel: Channel,
+        pressure: u8,
+    },
+    PitchBendChange {
+        channel: Channel,
+        value: i16,
+    },
+    MtcQuarterFrame(u8), // MIDI Time Code piece (message type and value nibbles)
+    SongPositionPointer(u16), // Position in MIDI beats (sixteenth notes) from song start
+    SongSelect(u8),
+    TuneRequest,
+    TimingClock,
+    Start,
+    Continue,
+    Stop,
+    ActiveSensing,
+    Meta(MetaEvent),
+    SysEx(Vec<u8>), // Bytes after the 0xF0 status, including the closing 0xF7 if present
+    SysExContinuation(Vec<u8>), // Bytes after a 0xF7 status: a SysEx continuation or escape
+}
+
+impl MidiMessage {
+    /// Channel of a channel voice message, or None for system and meta messages
+    pub fn channel(&self) -> Option<Channel> {
+        match *self {
+            MidiMessage::NoteOn { channel, .. }
+            | MidiMessage::NoteOff { channel, .. }
+            | MidiMessage::PolyphonicKeyPressure { channel, .. }
+            | MidiMessage::ControlChange { channel, .. }
+            | MidiMessage::ProgramChange { channel, .. }
+            | MidiMessage::ChannelPressure { channel, .. }
+            | MidiMessage::PitchBendChange { channel, .. } => Some(channel),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to the channel of a channel message
+    pub(crate) fn channel_mut(&mut self) -> Option<&mut Channel> {
+        match self {
+            MidiMessage::NoteOn { channel, .. }
+            | MidiMessage::NoteOff { channel, .. }
+            | MidiMessage::PolyphonicKeyPressure { channel, .. }
+            | MidiMessage::ControlChange { channel, .. }
+            | MidiMessage::ProgramChange { channel, .. }
+            | MidiMessage::ChannelPressure { channel, .. }
+            | MidiMessage::PitchBendChange { channel, .. } => Some(channel),
+            _ => None,
+        }
+    }
+
+    /// True for a NoteOn that starts a note (velocity above 0)
+    pub fn is_note_on(&self) -> bool {
+        matches!(self, MidiMessage::NoteOn { velocity, .. } if *velocity > 0)
+    }
+
+    /// True for a NoteOff, or a NoteOn with velocity 0
+    pub fn is_note_off(&self) -> bool {
+        matches!(
+            self,
+            MidiMessage::NoteOff { .. } | MidiMessage::NoteOn { velocity: 0, .. }
+        )
+    }
+
+    /// True for a meta event
+    pub fn is_meta(&self) -> bool {
+        matches!(self, MidiMessage::Meta(_))
+    }
+
+    /// True for a channel voice message, i.e. one that has a channel
+    pub fn is_channel_message(&self) -> bool {
+        self.channel().is_some()
+    }
+
+    /// True for the EndOfTrack meta event
+    pub(crate) fn is_end_of_track(&self) -> bool {
+        matches!(self, MidiMessage::Meta(MetaEvent::EndOfTrack))
+    }
+
+    /// Broad kind of the message, for filtering without matching every variant
+    pub fn category(&self) -> MessageCategory {
+        match self {
+            MidiMessage::NoteOn { .. }
+            | MidiMessage::NoteOff { .. }
+            | MidiMessage::PolyphonicKeyPressure { .. }
+            | MidiMessage::ControlChange { .. }
+            | MidiMessage::ProgramChange { .. }
+            | MidiMessage::ChannelPressure { .. }
+            | MidiMessage::PitchBendChange { .. } => MessageCategory::ChannelVoice,
+            MidiMessage::MtcQuarterFrame(_)
+            | MidiMessage::SongPositionPointer(_)
+            | MidiMessage::SongSelect(_)
+            | MidiMessage::TuneRequest => MessageCategory::SystemCommon,
+            MidiMessage::TimingClock
+            | MidiMessage::Start
+            | MidiMessage::Continue
+            | MidiMessage::Stop
+            | MidiMessage::ActiveSensing => MessageCategory::SystemRealTime,
+            MidiMessage::SysEx(_) | MidiMessage::SysExContinuation(_) => {
+                MessageCategory::SystemExclusive
+            }
+            MidiMessage::Meta(_) => MessageCategory::Meta,
+        }
+    }
+}
+
+/// Broad kinds of MIDI message, as returned by `MidiMessage::category`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MessageCategory {
+    ChannelVoice,    // Notes, controllers, programs, pressure and pitch bend
+    SystemCommon,    // MTC quarter frame, song position, song select, tune request
+    SystemRealTime,  // Clock, start, continue, stop, active sensing
+    SystemExclusive, // SysEx packets and their continuations
+    Meta,            // File-only meta events
+}
+
+impl TryFrom<&[u8]> for MidiMessage {
+    type Error = MidiError;
+
+    /// Decode a single message as sent over a MIDI connection, such as one read from a
+    /// live input, ignoring any bytes after it.
+    ///
+    /// The slice must start with a status byte. A SysEx runs from its 0xF0 to the
+    /// closing 0xF7, which is kept in the data as when parsing files. 0xFF means System
+    /// Reset on the wire rather than a meta event and is rejected, as is a lone 0xF7.
+    /// A message missing data bytes is an error, whether the bytes run out or another
+    /// status byte takes their place. A NoteOn with velocity 0 stays a NoteOn, so the
+    /// result of `to_bytes` decodes to the same message; treat it as a NoteOff.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let mut reader = SliceReader::new(bytes);
+        let status = reader.read_u8()?;
+        match status {
+            0x00..=0x7F => Err(MidiError::format_at(
+                &mut reader,
+                format!("Expected a status byte, found data byte 0x{:02X}", status),
+            )),
+            0xF0 => match bytes.iter().position(|&byte| byte == 0xF7) {
+                Some(end) => Ok(MidiMessage::SysEx(bytes[1..=end].to_vec())),
+                None => Err(MidiError::Format {
+                    message: "SysEx message has no closing 0xF7".to_string(),
+                    offset: bytes.len() as u64,
+                }),
+            },
+            0xF7 | 0xFF => Err(MidiError::unsupported_at(
+                &mut reader,
+                format!("Status 0x{:02X} is not a standalone message", status),
+            )),
+            // A status byte where a data byte belongs means the message was cut short,
+            // so data bytes are never masked here
+            _ => MidiFile::parse_message(
+                &mut reader,
+                status,
+                bytes.len() as u64,
+                &ParseOptions {
+                    strict_data_bytes: true,
+                    preserve_note_on_zero: true,
+                    ..ParseOptions::default()
+                },
+            ),
+        }
+    }
+}
+
+/// MIDI meta events
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MetaEvent {
+    SequenceNumber(u16),
+    Text(MetaText),
+    CopyrightNotice(MetaText),
+    TrackName(MetaText),
+    InstrumentName(MetaText),
+    Lyrics(MetaText),
+    Marker(MetaText),
+    CuePoint(MetaText),
+    ChannelPrefix(u8), // Channel that subsequent meta/sysex events apply to
+    MidiPort(u8),      // Output port for events on this track
+    EndOfTrack,
+    SetTempo(u32), // Microseconds per quarter note
+    SmpteOffset {
+        hours: u8, // Also encodes the frame rate in bits 5-6, as stored in the file
+        minutes: u8,
+        seconds: u8,
+        frames: u8,
+        subframes: u8, // Hundredths of a frame
+    },
+    TimeSignature {
+        numerator: u8,
+        denominator: u8,
+        clocks_per_metronome: u8,
+        thirty_seconds_per_quarter: u8,
+    },
+    KeySignature {
+        key: i8,   // -7 to 7 (negative = flats, positive = sharps)
+        scale: u8, // 0 = major, 1 = minor
+    },
+    SequencerSpecific(Vec<u8>),
+}
+
+#[derive(Error, Debug)]
+pub enum MidiError {
+    #[cfg(feature = "std")]
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid MIDI file at byte {offset}: {message}")]
+    Format { message: String, offset: u64 },
+
+    #[error("Unsupported MIDI feature at byte {offset}: {message}")]
+    Unsupported { message: String, offset: u64 },
+
+    #[error("Cannot encode MIDI data: {0}")]
+    Encode(String),
+
+    #[error("Invalid operation: {0}")]
+    InvalidOperation(String),
+}
+
+impl MidiError {
+    /// Format error at the reader's current position
+    fn format_at<S: MidiSource>(reader: &mut S, message: impl Into<String>) -> Self {
+        MidiError::Format {
+            message: message.into(),
+            offset: reader.position().unwrap_or_default(),
+        }
+    }
+
+    /// True if the input ended in the middle of something being read
+    #[cfg(feature = "std")]
+    fn is_unexpected_eof(&self) -> bool {
+        matches!(self, MidiError::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof)
+    }
+
+    /// True if the input ended in the middle of something being read
+    #[cfg(not(feature = "std"))]
+    fn is_unexpected_eof(&self) -> bool {
+        matches!(self, MidiError::Format { message, .. } if message == source::UNEXPECTED_EOF)
+    }
+
+    /// Unsupported-feature error at the reader's current position
+    fn unsupported_at<S: MidiSource>(reader: &mut S, message: impl Into<String>) -> Self {
+        MidiError::Unsupported {
+            message: message.into(),
+            offset: reader.position().unwrap_or_default(),
+        }
+    }
+}
+
+impl MidiFile {
+    /// Open and parse a MIDI file from the given path, reading it through a buffer.
+    ///
+    /// RMID files (`.rmi`) are unwrapped to the MIDI data inside, as by every other parse
+    /// function. With the `gzip` feature, gzip-compressed files (such as `.mid.gz`) are
+    /// detected by their magic number and decompressed first.
+    #[cfg(feature = "std")]
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, MidiError> {
+        Self::open_with_options(path, &ParseOptions::default())
+    }
+
+    /// Parse a MIDI file from any seekable reader, starting at its current position.
+    ///
+    /// The reader is buffered internally and its position tracked without seeking, so
+    /// an unbuffered `File` is read as fast as one wrapped in a `BufReader`.
+    #[cfg(feature = "std")]
+    pub fn read<R: Read + Seek>(reader: R) -> Result<Self, MidiError> {
+        Self::read_with_options(reader, &ParseOptions::default())
+    }
+
+    /// Open and parse a MIDI file from the given path using the given options
+    #[cfg(feature = "std")]
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: &ParseOptions,
+    ) -> Result<Self, MidiError> {
+        #[cfg_attr(not(feature = "gzip"), allow(unused_mut))]
+        let mut file = File::open(path)?;
+        // One byte past the limit is enough for the size check to reject the data
+        #[cfg(feature = "gzip")]
+        if let Some(data) =
+            gzip::decompress_if_gzip(&mut file, options.max_file_bytes.saturating_add(1))?
+        {
+            return Self::from_bytes_with_options(&data, options);
+        }
+        Self::parse(&mut BufferedSource::new(file)?, options)
+    }
+
+    /// Parse a MIDI file from any seekable reader using the given options
+    #[cfg(feature = "std")]
+    pub fn read_with_options<R: Read + Seek>(
+        reader: R,
+        options: &ParseOptions,
+    ) -> Result<Self, MidiError> {
+        Self::parse(&mut BufferedSource::new(reader)?, options)
+    }
+
+    /// Read just the MThd header, leaving the reader at the first track chunk
+    #[cfg(feature = "std")]
+    pub fn read_header<R: Read + Seek>(reader: &mut R) -> Result<MidiHeader, MidiError> {
+        Self::parse_header(reader, None)
+    }
+
+    /// Parse a MIDI file from any seekable reader, handing each event to `callback` with
+    /// its track index as it is read instead of building the tracks in memory.
+    ///
+    /// Returns the header once every track has been visited.
+    #[cfg(feature = "std")]
+    pub fn parse_with<R, F>(reader: R, callback: F) -> Result<MidiHeader, MidiError>
+    where
+        R: Read + Seek,
+        F: FnMut(usize, &MidiEvent),
+    {
+        Self::visit(
+            &mut BufferedSource::new(reader)?,
+            &ParseOptions::default(),
+            callback,
+        )
+    }
+
+    /// Parse a MIDI file held entirely in memory
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MidiError> {
+        Self::from_bytes_with_options(data, &ParseOptions::default())
+    }
+
+    /// Parse a MIDI file held entirely in memory using the given options
+    pub fn from_bytes_with_options(data: &[u8], options: &ParseOptions) -> Result<Self, MidiError> {
+        Self::parse(&mut SliceReader::new(data), options)
+    }
+
+    /// Open a MIDI file, skipping tracks that fail to parse instead of failing
+    #[cfg(feature = "std")]
+    pub fn open_lenient<P: AsRef<Path>>(path: P) -> Result<LenientParse, MidiError> {
+        #[cfg_attr(not(feature = "gzip"), allow(unused_mut))]
+        let mut file = File::open(path)?;
+        #[cfg(feature = "gzip")]
+        if let Some(data) = gzip::decompress_if_gzip(&mut file, u64::MAX)? {
+            return Self::from_bytes_lenient(&data);
+        }
+        Self::parse_lenient(&mut BufferedSource::new(file)?, &ParseOptions::default())
+    }
+
+    /// Parse a MIDI file from any seekable reader, skipping tracks that fail to parse
+    #[cfg(feature = "std")]
+    pub fn read_lenient<R: Read + Seek>(reader: R) -> Result<LenientParse, MidiError> {
+        Self::parse_lenient(&mut BufferedSource::new(reader)?, &ParseOptions::default())
+    }
+
+    /// Parse an in-memory MIDI file, skipping tracks that fail to parse
+    pub fn from_bytes_lenient(data: &[u8]) -> Result<LenientParse, MidiError> {
+        Self::parse_lenient(&mut SliceReader::new(data), &ParseOptions::default())
+    }
+
+    /// Open a MIDI file that may be truncated, keeping every complete event read before
+    /// the data runs out. Errors other than running out of input still fail the parse.
+    #[cfg(feature = "std")]
+    pub fn open_partial<P: AsRef<Path>>(path: P) -> Result<PartialParse, MidiError> {
+        #[cfg_attr(not(feature = "gzip"), allow(unused_mut))]
+        let mut file = File::open(path)?;
+        #[cfg(feature = "gzip")]
+        if let Some(data) = gzip::decompress_if_gzip(&mut file, u64::MAX)? {
+            return Self::from_bytes_partial(&data);
+        }
+        Self::parse_partial(&mut BufferedSource::new(file)?, &ParseOptions::default())
+    }
+
+    /// Parse a possibly truncated MIDI file from any seekable reader
+    #[cfg(feature = "std")]
+    pub fn read_partial<R: Read + Seek>(reader: R) -> Result<PartialParse, MidiError> {
+        Self::parse_partial(&mut BufferedSource::new(reader)?, &ParseOptions::default())
+    }
+
+    /// Parse a possibly truncated in-memory MIDI file
+    pub fn from_bytes_partial(data: &[u8]) -> Result<PartialParse, MidiError> {
+        Self::parse_partial(&mut SliceReader::new(data), &ParseOptions::default())
+    }
+
+    /// Parse a complete MIDI file from the given source
+    fn parse<S: MidiSource>(reader: &mut S, options: &ParseOptions) -> Result<Self, MidiError> {
+        let header = Self::parse_header(reader, None)?;
+        let capacity = Self::check_limits(reader, &header, options)?;
+
+        // Parse tracks, collecting any other chunks found along the way
+        let mut tracks = Vec::with_capacity(capacity);
+        let mut chunks = Vec::new();
+        for _ in 0..header.num_tracks {
+            tracks.push(Self::parse_track(
+                reader,
+                options,
+                &mut chunks,
+                &mut Vec::new(),
+            )?);
+        }
+
+        Ok(MidiFile {
+            header,
+            tracks,
+            chunks,
+        })
+    }
+
+    /// Parse a MIDI file from the given source, passing events to `callback` as they are read
+    #[cfg(feature = "std")]
+    fn visit<S, F>(
+        reader: &mut S,
+        options: &ParseOptions,
+        mut callback: F,
+    ) -> Result<MidiHeader, MidiError>
+    where
+        S: MidiSource,
+        F: FnMut(usize, &MidiEvent),
+    {
+        let header = Self::parse_header(reader, None)?;
+        Self::check_limits(reader, &header, options)?;
+
+        for index in 0..header.num_tracks as usize {
+            let (track_start_pos, track_length) = Self::next_track_chunk(reader, &mut Vec::new())?;
+            let mut state = TrackState::new(reader, track_start_pos, track_length)?;
+            while let Some(event) = state.next_event(reader, options) {
+                callback(index, &event?);
+            }
+        }
+
+        Ok(header)
+    }
+
+    /// Parse a MIDI file, stopping without an error where the input runs out
+    fn parse_partial<S: MidiSource>(
+        reader: &mut S,
+        options: &ParseOptions,
+    ) -> Result<PartialParse, MidiError> {
+        let header = Self::parse_header(reader, None)?;
+        let capacity = Self::check_limits(reader, &header, options)?;
+
+        let mut tracks = Vec::with_capacity(capacity);
+        let mut chunks = Vec::new();
+        let mut truncated = false;
+        for _ in 0..header.num_tracks {
+            let (track_start_pos, track_length) = match Self::next_track_chunk(reader, &mut chunks)
+            {
+                Ok(bounds) => bounds,
+                Err(err) if err.is_unexpected_eof() => {
+                    truncated = true;
+                    break;
+                }
+                Err(err) => return Err(err),
+            };
+
+            // A track cut off by the end of the input fails on its last, partial event
+            let mut state = TrackState::new(reader, track_start_pos, track_length)?;
+            let mut events = Vec::new();
+            while let Some(event) = state.next_event(reader, options) {
+                match event {
+                    Ok(event) => events.push(event),
+                    Err(err) if err.is_unexpected_eof() || state.past_eof() => break,
+                    Err(err) => return Err(err),
+                }
+            }
+            tracks.push(MidiTrack { events });
+
+            if state.past_eof() {
+                truncated = true;
+                break;
+            }
+        }
+
+        Ok(PartialParse {
+            file: MidiFile {
+                header,
+                tracks,
+                chunks,
+            },
+            truncated,
+        })
+    }
+
+    /// Parse a MIDI file, recording per-track errors and moving on to the next track
+    fn parse_lenient<S: MidiSource>(
+        reader: &mut S,
+        options: &ParseOptions,
+    ) -> Result<LenientParse, MidiError> {
+        let mut warnings = Vec::new();
+        let header = Self::parse_header(reader, Some(&mut warnings))?;
+        let capacity = Self::check_limits(reader, &header, options)?;
+
+        let mut tracks = Vec::with_capacity(capacity);
+        let mut chunks = Vec::new();
+        let mut track_errors = Vec::new();
+        let mut running_status = Vec::new();
+        for index in 0..header.num_tracks as usize {
+            // Without a readable chunk header there is no way to find the next track
+            let (track_start_pos, track_length) = match Self::next_track_chunk(reader, &mut chunks)
+            {
+                Ok(bounds) => bounds,
+                Err(err) => {
+                    track_errors.push((index, err));
+                    break;
+                }
+            };
+
+            match Self::parse_track_events(
+                reader,
+                track_start_pos,
+                track_length,
+                options,
+                &mut warnings,
+            ) {
+                Ok((track, stats)) => {
+                    tracks.push(track);
+                    running_status.push(stats);
+                }
+                Err(err) => {
+                    track_errors.push((index, err));
+                    reader.seek_to(track_start_pos + track_length)?;
+                }
+            }
+        }
+
+        Ok(LenientParse {
+            file: MidiFile {
+                header,
+                tracks,
+                chunks,
+            },
+            track_errors,
+            warnings,
+            running_status,
+        })
+    }
+
+    /// Parse the MThd header chunk, skipping any bytes beyond the standard six.
+    ///
+    /// A time division of zero ticks is a Format error, unless `warnings` is given
+    /// (lenient parsing), where it is replaced by `DEFAULT_TIME_DIVISION` with a warning.
+    fn parse_header<S: MidiSource>(
+        reader: &mut S,
+        mut warnings: Option<&mut Vec<ParseWarning>>,
+    ) -> Result<MidiHeader, MidiError> {
+        // Parse header chunk
+        Self::skip_rmid_wrapper(reader)?;
+        Self::validate_chunk_header(reader, b"MThd")?;
+
+        // Read header length (should be 6, but some exporters write more)
+        let header_length = reader.read_u32_be()?;
+        if header_length < 6 {
+            return Err(MidiError::format_at(
+                reader,
+                format!("Invalid header length: {}", header_length),
+            ));
+        }
+
+        // Read header data
+        let format = reader.read_u16_be()?;
+        let num_tracks = reader.read_u16_be()?;
+        let time_division = reader.read_u16_be()?;
+
+        if header_length > 6 {
+            let pos = reader.position()?;
+            reader.seek_to(pos + (header_length - 6) as u64)?;
+            if let Some(warnings) = warnings.as_deref_mut() {
+                warnings.push(ParseWarning::HeaderLength {
+                    length: header_length,
+                });
+            }
+        }
+
+        // Check format is supported
+        if format > 2 {
+            return Err(MidiError::format_at(
+                reader,
+                format!("Unsupported MIDI format: {}", format),
+            ));
+        }
+
+        let mut header = MidiHeader {
+            format,
+            num_tracks,
+            time_division,
+        };
+
+        if header.has_zero_division() {
+            match warnings {
+                Some(warnings) => {
+                    warnings.push(ParseWarning::ZeroTimeDivision {
+                        time_division,
+                        replacement: DEFAULT_TIME_DIVISION,
+                    });
+                    header.time_division = DEFAULT_TIME_DIVISION;
+                }
+                None => {
+                    return Err(MidiError::format_at(
+                        reader,
+                        format!("Zero time division: 0x{:04X}", time_division),
+                    ))
+                }
+            }
+        }
+
+        Ok(header)
+    }
+
+    /// If the input is an RMID file (a Standard MIDI File wrapped in a RIFF container, as
+    /// in `.rmi` files), move to the start of its `data` chunk; otherwise leave the reader
+    /// where it was
+    fn skip_rmid_wrapper<S: MidiSource>(reader: &mut S) -> Result<(), MidiError> {
+        let start_pos = reader.position()?;
+        let mut id = [0u8; 4];
+        reader.read_exact(&mut id)?;
+        if &id != b"RIFF" {
+            return reader.seek_to(start_pos);
+        }
+
+        // RIFF sizes are little-endian, unlike everything in the SMF itself
+        let mut size = [0u8; 4];
+        reader.read_exact(&mut size)?;
+        let mut form = [0u8; 4];
+        reader.read_exact(&mut form)?;
+        if &form != b"RMID" {
+            return Err(MidiError::format_at(
+                reader,
+                format!(
+                    "Expected an RMID RIFF file, found form {:?}",
+                    String::from_utf8_lossy(&form)
+                ),
+            ));
+        }
+
+        loop {
+            reader.read_exact(&mut id)?;
+            reader.read_exact(&mut size)?;
+            if &id == b"data" {
+                return Ok(());
+            }
+            // Chunks are padded to an even length
+            let size = u32::from_le_bytes(size) as u64;
+            let pos = reader.position()?;
+            reader.seek_to(pos + size + size % 2)?;
+        }
+    }
+
+    /// Enforce the size limits in `options`, returning how many tracks it is safe to
+    /// allocate room for up front
+    fn check_limits<S: MidiSource>(
+        reader: &mut S,
+        header: &MidiHeader,
+        options: &ParseOptions,
+    ) -> Result<usize, MidiError> {
+        let total_len = reader.total_len()?;
+        if total_len > options.max_file_bytes {
+            return Err(MidiError::format_at(
+                reader,
+                format!(
+                    "Input is {} bytes, more than the limit of {}",
+                    total_len, options.max_file_bytes
+                ),
+            ));
+        }
+        if header.num_tracks as usize > options.max_tracks {
+            return Err(MidiError::format_at(
+                reader,
+                format!(
+                    "Header declares {} tracks, more than the limit of {}",
+                    header.num_tracks, options.max_tracks
+                ),
+            ));
+        }
+
+        // Every track chunk takes at least its 8-byte header, so a corrupt track count
+        // cannot reserve more tracks than the input could hold
+        let remaining = total_len.saturating_sub(reader.position()?);
+        Ok((header.num_tracks as u64).min(remaining / 8) as usize)
+    }
+
+    /// Validate a chunk header matches the expected type
+    fn validate_chunk_header<S: MidiSource>(
+        reader: &mut S,
+        expected: &[u8; 4],
+    ) -> Result<(), MidiError> {
+        let mut chunk_type = [0u8; 4];
+        reader.read_exact(&mut chunk_type)?;
+
+        if chunk_type != *expected {
+            return Err(MidiError::format_at(
+                reader,
+                format!(
+                    "Expected chunk type {:?}, found {:?}",
+                    core::str::from_utf8(expected).unwrap_or("????"),
+                    core::str::from_utf8(&chunk_type).unwrap_or("????")
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Parse the next MIDI track, storing any other chunks before it in `chunks`
+    fn parse_track<S: MidiSource>(
+        reader: &mut S,
+        options: &ParseOptions,
+        chunks: &mut Vec<RawChunk>,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Result<MidiTrack, MidiError> {
+        let (track_start_pos, track_length) = Self::next_track_chunk(reader, chunks)?;
+        let (track, _) =
+            Self::parse_track_events(reader, track_start_pos, track_length, options, warnings)?;
+        Ok(track)
+    }
+
+    /// Read chunks up to the next MTrk chunk header, keeping the others in `chunks`, and
+    /// return the start position and length of the track data
+    fn next_track_chunk<S: MidiSource>(
+        reader: &mut S,
+        chunks: &mut Vec<RawChunk>,
+    ) -> Result<(u64, u64), MidiError> {
+        loop {
+            let mut id = [0u8; 4];
+            reader.read_exact(&mut id)?;
+            if !Self::is_chunk_id(&id) {
+                return Err(MidiError::format_at(
+                    reader,
+                    format!("Expected a chunk type, found {:02X?}", id),
+                ));
+            }
+
+            let length = reader.read_u32_be()? as u64;
+            let start_pos = reader.position()?;
+            if &id == b"MTrk" {
+                return Ok((start_pos, length));
+            }
+
+            if length > reader.total_len()?.saturating_sub(start_pos) {
+                return Err(MidiError::format_at(
+                    reader,
+                    format!(
+                        "{} chunk runs past the end of the input",
+                        String::from_utf8_lossy(&id)
+                    ),
+                ));
+            }
+            let mut data = vec![0; length as usize];
+            reader.read_exact(&mut data)?;
+            chunks.push(RawChunk { id, data });
+        }
+    }
+
+    /// True if `id` can be a chunk type: four printable ASCII characters
+    pub(crate) fn is_chunk_id(id: &[u8; 4]) -> bool {
+        id.iter().all(|byte| (0x20..0x7F).contains(byte))
+    }
+
+    /// Parse an MTrk chunk header, returning the start position and length of its data
+    #[cfg(feature = "std")]
+    fn parse_track_header<S: MidiSource>(reader: &mut S) -> Result<(u64, u64), MidiError> {
+        // Validate track header
+        Self::validate_chunk_header(reader, b"MTrk")?;
+
+        // Read track length
+        let track_length = reader.read_u32_be()? as u64;
+        let track_start_pos = reader.position()?;
+
+        Ok((track_start_pos, track_length))
+    }
+
+    /// Parse the events of a track whose data starts at `track_start_pos`, along with
+    /// how often its channel messages used running status
+    fn parse_track_events<S: MidiSource>(
+        reader: &mut S,
+        track_start_pos: u64,
+        track_length: u64,
+        options: &ParseOptions,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Result<(MidiTrack, RunningStatusStats), MidiError> {
+        // Read all events in the track
+        let mut state = TrackState::new(reader, track_start_pos, track_length)?;
+        let mut events = Vec::new();
+        while let Some(event) = state.next_event(reader, options) {
+            events.push(event?);
+        }
+        warnings.extend(state.take_warnings());
+
+        Ok((MidiTrack { events }, state.running_status_stats()))
+    }
+
+    /// Parse a single MIDI event, reading no further than `end_pos`, counting its
+    /// status byte in `stats`
+    fn parse_event<S: MidiSource>(
+        reader: &mut S,
+        running_status: &mut Option<u8>,
+        stats: &mut RunningStatusStats,
+        end_pos: u64,
+        options: &ParseOptions,
+    ) -> Result<MidiEvent, MidiError> {
+        // Read variable-length delta time
+        let delta_time = Self::read_variable_length(reader)?;
+
+        // Read status byte or use running status
+        let mut status = reader.read_u8()?;
+
+        // If the high bit is not set, this is data and we should use running status
+        if status < 0x80 {
+            if let Some(rs) = running_status {
+                // Put back the byte we just read (it's actually data)
+                reader.unread_byte()?;
+                status = *rs;
+                stats.running += 1;
+            } else {
+                return Err(MidiError::format_at(
+                    reader,
+                    "Unexpected data byte without running status".to_string(),
+                ));
+            }
+        } else {
+            // Channel messages set running status; System messages cancel it,
+            // except Real-Time messages which may be interleaved freely
+            if status < 0xF0 {
+                *running_status = Some(status);
+                stats.explicit += 1;
+            } else if !matches!(status, 0xF8..=0xFE) {
+                *running_status = None;
+            }
+        }
+
+        // Parse message based on status byte
+        let message = Self::parse_message(reader, status, end_pos, options)?;
+
+        Ok(MidiEvent {
+            delta_time,
+            message,
+        })
+    }
+
+    /// Parse a MIDI message based on its status byte
+    fn parse_message<S: MidiSource>(
+        reader: &mut S,
+        status: u8,
+        end_pos: u64,
+        options: &ParseOptions,
+    ) -> Result<MidiMessage, MidiError> {
+        match status {
+            // Note Off: 0x80-0x8F
+            0x80..=0x8F => {
+                let channel = Channel::from_status(status);
+                let note = Self::read_data_byte(reader, options)?;
+                let velocity = Self::read_data_byte(reader, options)?;
+                Ok(MidiMessage::NoteOff {
+                    channel,
+                    note,
+                    velocity,
+                })
+            }
+
+            // Note On: 0x90-0x9F
+            0x90..=0x9F => {
+                let channel = Channel::from_status(status);
+                let note = Self::read_data_byte(reader, options)?;
+                let velocity = Self::read_data_byte(reader, options)?;
+                // Note-on with velocity 0 is equivalent to note-off
+                if velocity == 0 && !options.preserve_note_on_zero {
+                    Ok(MidiMessage::NoteOff {
+                        channel,
+                        note,
+                        velocity,
+                    })
+                } else {
+                    Ok(MidiMessage::NoteOn {
+                        channel,
+                        note,
+                        velocity,
+                    })
+                }
+            }
+
+            // Polyphonic Key Pressure: 0xA0-0xAF
+            0xA0..=0xAF => {
+                let channel = Channel::from_status(status);
+                let note = Self::read_data_byte(reader, options)?;
+                let pressure = Self::read_data_byte(reader, options)?;
+                Ok(MidiMessage::PolyphonicKeyPressure {
+                    channel,
+                    note,
+                    pressure,
+                })
+            }
+
+            // Control Change: 0xB0-0xBF
+            0xB0..=0xBF => {
+                let channel = Channel::from_status(status);
+                let controller = Self::read_data_byte(reader, options)?;
+                let value = Self::read_data_byte(reader, options)?;
+                Ok(MidiMessage::ControlChange {
+                    channel,
+                    controller,
+                    value,
+                })
+            }
+
+            // Program Change: 0xC0-0xCF
+            0xC0..=0xCF => {
+                let channel = Channel::from_status(status);
+                let program = Self::read_data_byte(reader, options)?;
+                Ok(MidiMessage::ProgramChange { channel, program })
+            }
+
+            // Channel Pressure: 0xD0-0xDF
+            0xD0..=0xDF => {
+                let channel = Channel::from_status(status);
+                let pressure = Self::read_data_byte(reader, options)?;
+                Ok(MidiMessage::ChannelPressure { channel, pressure })
+            }
+
+            // Pitch Bend: 0xE0-0xEF
+            0xE0..=0xEF => {
+                let channel = Channel::from_status(status);
+                // Two 7-bit data bytes, least significant first, unlike the big-endian
+                // fields of the file format
+                let lsb = Self::read_data_byte(reader, options)? as u16;
+                let msb = Self::read_data_byte(reader, options)? as u16;
+                let value = ((msb << 7) | lsb) as i16 - 8192; // Center value at 0
+                Ok(MidiMessage::PitchBendChange { channel, value })
+            }
+
+            // System Exclusive: 0xF0, stored in files as a length-prefixed packet
+            0xF0 => {
+                let data = Self::read_length_prefixed(reader, end_pos)?;
+                Ok(MidiMessage::SysEx(data))
+            }
+
+            // SysEx continuation or escaped bytes: 0xF7, also length-prefixed
+            0xF7 => {
+                let data = Self::read_length_prefixed(reader, end_pos)?;
+                Ok(MidiMessage::SysExContinuation(data))
+            }
+
+            // MTC Quarter Frame: 0xF1
+            0xF1 => {
+                let value = Self::read_data_byte(reader, options)?;
+                Ok(MidiMessage::MtcQuarterFrame(value))
+            }
+
+            // Song Position Pointer: 0xF2
+            0xF2 => {
+                let lsb = Self::read_data_byte(reader, options)? as u16;
+                let msb = Self::read_data_byte(reader, options)? as u16;
+                Ok(MidiMessage::SongPositionPointer((msb << 7) | lsb))
+            }
+
+            // Song Select: 0xF3
+            0xF3 => {
+                let song = Self::read_data_byte(reader, options)?;
+                Ok(MidiMessage::SongSelect(song))
+            }
+
+            // Tune Request: 0xF6
+            0xF6 => Ok(MidiMessage::TuneRequest),
+
+            // System Real-Time: 0xF8-0xFE (0xFF is a meta event in files, not System Reset)
+            0xF8 => Ok(MidiMessage::TimingClock),
+            0xFA => Ok(MidiMessage::Start),
+            0xFB => Ok(MidiMessage::Continue),
+            0xFC => Ok(MidiMessage::Stop),
+            0xFE => Ok(MidiMessage::ActiveSensing),
+
+            // Meta Event: 0xFF
+            0xFF => {
+                let meta_type = reader.read_u8()?;
+                let data = Self::read_length_prefixed(reader, end_pos)?;
+                let length = data.len();
+
+                match meta_type {
+                    0x00 => {
+                        if length != 2 {
+                            return Err(MidiError::format_at(
+                                reader,
+                                "Invalid sequence number length".to_string(),
+                            ));
+                        }
+                        let value = u16::from_be_bytes([data[0], data[1]]);
+                        Ok(MidiMessage::Meta(MetaEvent::SequenceNumber(value)))
+                    }
+                    0x01 => Ok(MidiMessage::Meta(MetaEvent::Text(MetaText::parsed(
+                        data, options,
+                    )))),
+                    0x02 => Ok(MidiMessage::Meta(MetaEvent::CopyrightNotice(
+                        MetaText::parsed(data, options),
+                    ))),
+                    0x03 => Ok(MidiMessage::Meta(MetaEvent::TrackName(MetaText::parsed(
+                        data, options,
+                    )))),
+                    0x04 => Ok(MidiMessage::Meta(MetaEvent::InstrumentName(
+                        MetaText::parsed(data, options),
+                    ))),
+                    0x05 => Ok(MidiMessage::Meta(MetaEvent::Lyrics(MetaText::parsed(
+                        data, options,
+                    )))),
+                    0x06 => Ok(MidiMessage::Meta(MetaEvent::Marker(MetaText::parsed(
+                        data, options,
+                    )))),
+                    0x07 => Ok(MidiMessage::Meta(MetaEvent::CuePoint(MetaText::parsed(
+                        data, options,
+                    )))),
+                    0x20 => {
+                        if length != 1 {
+                            return Err(MidiError::format_at(
+                                reader,
+                                "Invalid channel prefix length".to_string(),
+                            ));
+                        }
+                        if data[0] > 15 {
+                            return Err(MidiError::format_at(
+                                reader,
+                                format!("Invalid channel prefix: {}", data[0]),
+                            ));
+                        }
+                        Ok(MidiMessage::Meta(MetaEvent::ChannelPrefix(data[0])))
+                    }
+                    0x21 => {
+                        if length != 1 {
+                            return Err(MidiError::format_at(
+                                reader,
+                                "Invalid MIDI port length".to_string(),
+                            ));
+                        }
+                        Ok(MidiMessage::Meta(MetaEvent::MidiPort(data[0])))
+                    }
+                    0x2F => {
+                        if length != 0 {
+                            return Err(MidiError::format_at(
+                                reader,
+                                "End of track event with non-zero length".to_string(),
+                            ));
+                        }
+                        Ok(MidiMessage::Meta(MetaEvent::EndOfTrack))
+                    }
+                    0x51 => {
+                        if length != 3 {
+                            return Err(MidiError::format_at(
+                                reader,
+                                "Invalid tempo event length".to_string(),
+                            ));
+                        }
+                        // 24-bit big-endian, like every multi-byte value in the file
+                        let tempo = u32::from_be_bytes([0, data[0], data[1], data[2]]);
+                        Ok(MidiMessage::Meta(MetaEvent::SetTempo(tempo)))
+                    }
+                    0x54 => {
+                        if length != 5 {
+                            return Err(MidiError::format_at(
+                                reader,
+                                "Invalid SMPTE offset length".to_string(),
+                            ));
+                        }
+                        Ok(MidiMessage::Meta(MetaEvent::SmpteOffset {
+                            hours: data[0],
+                            minutes: data[1],
+                            seconds: data[2],
+                            frames: data[3],
+                            subframes: data[4],
+                        }))
+                    }
+                    0x58 => {
+                        if length != 4 {
+                            return Err(MidiError::format_at(
+                                reader,
+                                "Invalid time signature length".to_string(),
+                            ));
+                        }
+                        // The denominator is stored as a power of two
+                        let denominator = 1u8.checked_shl(data[1] as u32).ok_or_else(|| {
+                            MidiError::format_at(
+                                reader,
+                                format!("Invalid time signature denominator: 2^{}", data[1]),
+                            )
+                        })?;
+                        Ok(MidiMessage::Meta(MetaEvent::TimeSignature {
+                            numerator: data[0],
+                            denominator,
+                            clocks_per_metronome: data[2],
+                            thirty_seconds_per_quarter: data[3],
+                        }))
+                    }
+                    0x59 => {
+                        if length != 2 {
+                            return Err(MidiError::format_at(
+                                reader,
+                                "Invalid key signature length".to_string(),
+                            ));
+                        }
+                        Ok(MidiMessage::Meta(MetaEvent::KeySignature {
+                            key: data[0] as i8,
+                            scale: data[1],
+                        }))
+                    }
+                    0x7F => Ok(MidiMessage::Meta(MetaEvent::SequencerSpecific(data))),
+                    _ => Err(MidiError::unsupported_at(
+                        reader,
+                        format!("Unsupported meta event type: {}", meta_type),
+                    )),
+                }
+            }
+
+            // Unsupported message type
+            _ => Err(MidiError::unsupported_at(
+                reader,
+                format!("Unsupported MIDI message type: 0x{:02X}", status),
+            )),
+        }
+    }
+
+    /// Read a data byte, which must have its high bit clear: strict parsing rejects one
+    /// that does not, otherwise the bit is masked off
+    fn read_data_byte<S: MidiSource>(
+        reader: &mut S,
+        options: &ParseOptions,
+    ) -> Result<u8, MidiError> {
+        let byte = reader.read_u8()?;
+        if byte & 0x80 == 0 {
+            return Ok(byte);
+        }
+        if options.strict_data_bytes {
+            return Err(MidiError::format_at(
+                reader,
+                format!("Data byte with high bit set: 0x{:02X}", byte),
+            ));
+        }
+        Ok(byte & 0x7F)
+    }
+
+    /// Read a variable-length size followed by that many bytes, rejecting sizes that
+    /// run past `end_pos` before anything is allocated.
+    ///
+    /// Track parsing passes the end of the input rather than of the track, so an event
+    /// crossing a declared length that is too short can still be read and the track
+    /// recovered, as for `ParseWarning::TrackLengthTooShort`.
+    fn read_length_prefixed<S: MidiSource>(
+        reader: &mut S,
+        end_pos: u64,
+    ) -> Result<Vec<u8>, MidiError> {
+        let length = Self::read_variable_length(reader)? as u64;
+        let remaining = end_pos.saturating_sub(reader.position()?);
+        if length > remaining {
+            return Err(MidiError::format_at(
+                reader,
+                format!(
+                    "Event length {} exceeds the {} bytes left in the input",
+                    length, remaining
+                ),
+            ));
+        }
+
+        let mut data = vec![0; length as usize];
+        reader.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    /// Read a variable-length quantity
+    fn read_variable_length<S: MidiSource>(reader: &mut S) -> Result<u32, MidiError> {
+        let mut value: u32 = 0;
+        // A quantity is at most four bytes long, so it always fits in 28 bits
+        for _ in 0..4 {
+            let byte = reader.read_u8()?;
+            value = (value << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(MidiError::format_at(
+            reader,
+            "Variable-length quantity longer than four bytes".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file with the given header fields and one track holding `events`, which must
+    /// end with an EndOfTrack
+    fn file_with_track(format: u16, time_division: u16, events: &[u8]) -> Vec<u8> {
+        let mut data = b"MThd\0\0\0\x06".to_vec();
+        data.extend_from_slice(&format.to_be_bytes());
+        data.extend_from_slice(&1u16.to_be_bytes());
+        data.extend_from_slice(&time_division.to_be_bytes());
+        data.extend_from_slice(b"MTrk");
+        data.extend_from_slice(&(events.len() as u32).to_be_bytes());
+        data.extend_from_slice(events);
+        data
+    }
+
+    /// The messages of the only track in `data`
+    fn messages(data: &[u8]) -> Vec<MidiMessage> {
+        let file = MidiFile::from_bytes(data).unwrap();
+        file.tracks[0]
+            .events
+            .iter()
+            .map(|e| e.message.clone())
+            .collect()
+    }
+
+    const END_OF_TRACK: [u8; 4] = [0x00, 0xFF, 0x2F, 0x00];
+
+    #[test]
+    fn header_fields_are_big_endian() {
+        let file = MidiFile::from_bytes(&file_with_track(0x0001, 0x01E0, &END_OF_TRACK)).unwrap();
+        assert_eq!(file.header.format, 1);
+        assert_eq!(file.header.num_tracks, 1);
+        assert_eq!(file.header.time_division, 480);
+
+        // Byte-swapped, format 1 would read as 256 and be rejected
+        assert!(MidiFile::from_bytes(&file_with_track(0x0100, 0x01E0, &END_OF_TRACK)).is_err());
+    }
+
+    #[test]
+    fn tempo_is_24_bit_big_endian() {
+        let mut events = vec![0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20];
+        events.extend_from_slice(&[0x00, 0xFF, 0x51, 0x03, 0x01, 0x02, 0x03]);
+        events.extend_from_slice(&END_OF_TRACK);
+        assert_eq!(
+            messages(&file_with_track(0, 96, &events))[..2],
+            [
+                MidiMessage::Meta(MetaEvent::SetTempo(500_000)),
+                MidiMessage::Meta(MetaEvent::SetTempo(0x01_02_03)),
+            ]
+        );
+    }
+
+    #[test]
+    fn sequence_number_is_big_endian() {
+        let mut events = vec![0x00, 0xFF, 0x00, 0x02, 0x12, 0x34];
+        events.extend_from_slice(&END_OF_TRACK);
+        assert_eq!(
+            messages(&file_with_track(0, 96, &events))[0],
+            MidiMessage::Meta(MetaEvent::SequenceNumber(0x1234))
+        );
+    }
+
+    #[test]
+    fn pitch_bend_is_lsb_first() {
+        let mut events = Vec::new();
+        for (lsb, msb) in [
+            (0x00, 0x40),
+            (0x7F, 0x7F),
+            (0x00, 0x00),
+            (0x01, 0x00),
+            (0x00, 0x01),
+        ] {
+            events.extend_from_slice(&[0x00, 0xE3, lsb, msb]);
+        }
+        events.extend_from_slice(&END_OF_TRACK);
+        let channel = Channel::new(3).unwrap();
+        let values: Vec<MidiMessage> = [0, 8191, -8192, -8191, -8064]
+            .into_iter()
+            .map(|value| MidiMessage::PitchBendChange { channel, value })
+            .collect();
+        assert_eq!(messages(&file_with_track(0, 96, &events))[..5], values[..]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn multi_byte_values_round_trip() {
+        let mut events = vec![0x00, 0xFF, 0x00, 0x02, 0xAB, 0xCD];
+        events.extend_from_slice(&[0x00, 0xFF, 0x51, 0x03, 0x0F, 0x42, 0x40]);
+        events.extend_from_slice(&[0x00, 0xE0, 0x12, 0x34]);
+        events.extend_from_slice(&END_OF_TRACK);
+        let data = file_with_track(1, 0x01E0, &events);
+        let mut written = Vec::new();
+        MidiFile::from_bytes(&data)
+            .unwrap()
+            .write(&mut written)
+            .unwrap();
+        assert_eq!(written, data);
+    }
+
+    #[test]
+    fn data_byte_reuses_channel_status() {
+        let mut events = vec![0x00, 0x90, 0x3C, 0x40, 0x00, 0x3E, 0x40];
+        events.extend_from_slice(&END_OF_TRACK);
+        let channel = Channel::new(0).unwrap();
+        assert_eq!(
+            messages(&file_with_track(0, 96, &events))[..2],
+            [
+                MidiMessage::NoteOn {
+                    channel,
+                    note: 0x3C,
+                    velocity: 0x40
+                },
+                MidiMessage::NoteOn {
+                    channel,
+                    note: 0x3E,
+                    velocity: 0x40
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn meta_event_cancels_running_status() {
+        let mut events = vec![0x00, 0x90, 0x3C, 0x40];
+        events.extend_from_slice(&[0x00, 0xFF, 0x01, 0x00]);
+        events.extend_from_slice(&[0x00, 0x3E, 0x40]);
+        events.extend_from_slice(&END_OF_TRACK);
+        assert!(matches!(
+            MidiFile::from_bytes(&file_with_track(0, 96, &events)),
+            Err(MidiError::Format { .. })
+        ));
+    }
+
+    #[test]
+    fn sysex_cancels_running_status() {
+        let mut events = vec![0x00, 0x90, 0x3C, 0x40];
+        events.extend_from_slice(&[0x00, 0xF0, 0x02, 0x7E, 0xF7]);
+        events.extend_from_slice(&[0x00, 0x3E, 0x40]);
+        events.extend_from_slice(&END_OF_TRACK);
+        assert!(matches!(
+            MidiFile::from_bytes(&file_with_track(0, 96, &events)),
+            Err(MidiError::Format { .. })
+        ));
+    }
+
+    #[test]
+    fn real_time_messages_decode() {
+        let mut events = Vec::new();
+        for status in [0xF8, 0xFA, 0xFB, 0xFC, 0xFE] {
+            events.extend_from_slice(&[0x00, status]);
+        }
+        events.extend_from_slice(&END_OF_TRACK);
+        assert_eq!(
+            messages(&file_with_track(0, 96, &events)),
+            [
+                MidiMessage::TimingClock,
+                MidiMessage::Start,
+                MidiMessage::Continue,
+                MidiMessage::Stop,
+                MidiMessage::ActiveSensing,
+                MidiMessage::Meta(MetaEvent::EndOfTrack),
+            ]
+        );
+        assert_eq!(
+            MidiMessage::try_from(&[0xF8][..]).unwrap(),
+            MidiMessage::TimingClock
+        );
+    }
+
+    #[test]
+    fn real_time_message_keeps_running_status() {
+        let mut events = vec![0x00, 0x90, 0x3C, 0x40];
+        events.extend_from_slice(&[0x00, 0xF8]);
+        events.extend_from_slice(&[0x00, 0x3E, 0x40]);
+        events.extend_from_slice(&END_OF_TRACK);
+        assert_eq!(
+            messages(&file_with_track(0, 96, &events))[2],
+            MidiMessage::NoteOn {
+                channel: Channel::new(0).unwrap(),
+                note: 0x3E,
+                velocity: 0x40
+            }
+        );
+    }
+
+    #[test]
+    fn channel_prefix_and_port_decode() {
+        let mut events = vec![0x00, 0xFF, 0x20, 0x01, 0x09];
+        events.extend_from_slice(&[0x00, 0xFF, 0x21, 0x01, 0x02]);
+        events.extend_from_slice(&END_OF_TRACK);
+        assert_eq!(
+            messages(&file_with_track(0, 96, &events))[..2],
+            [
+                MidiMessage::Meta(MetaEvent::ChannelPrefix(9)),
+                MidiMessage::Meta(MetaEvent::MidiPort(2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn channel_prefix_and_port_need_one_byte() {
+        for meta in [
+            [0xFF, 0x20, 0x02, 0x09, 0x00],
+            [0xFF, 0x21, 0x02, 0x02, 0x00],
+        ] {
+            let mut events = vec![0x00];
+            events.extend_from_slice(&meta);
+            events.extend_from_slice(&END_OF_TRACK);
+            assert!(matches!(
+                MidiFile::from_bytes(&file_with_track(0, 96, &events)),
+                Err(MidiError::Format { .. })
+            ));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips() {
+        let mut events = vec![0x00, 0xF0, 0x03, 0x7E, 0x09, 0xF7];
+        events.extend_from_slice(&[0x00, 0xFF, 0x03, 0x04, b'L', b'e', b'a', b'd']);
+        events.extend_from_slice(&[0x00, 0xFF, 0x7F, 0x02, 0x00, 0x41]);
+        events.extend_from_slice(&[0x10, 0xC1, 0x05, 0x00, 0xE1, 0x00, 0x40]);
+        events.extend_from_slice(&END_OF_TRACK);
+        let file = MidiFile::from_bytes(&file_with_track(1, 480, &events)).unwrap();
+
+        let json = serde_json::to_string(&file).unwrap();
+        assert_eq!(serde_json::from_str::<MidiFile>(&json).unwrap(), file);
+    }
+
+    #[test]
+    fn huge_event_length_errors_cleanly() {
+        // A length of 0xFFFFFFF, the largest a variable-length quantity can hold
+        for prefix in [&[0x00, 0xFF, 0x01][..], &[0x00, 0xF0], &[0x00, 0xF7]] {
+            let mut events = prefix.to_vec();
+            events.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0x7F, b'x']);
+            events.extend_from_slice(&END_OF_TRACK);
+            let data = file_with_track(0, 96, &events);
+            assert!(matches!(
+                MidiFile::from_bytes(&data),
+                Err(MidiError::Format { .. })
+            ));
+            assert!(matches!(
+                MidiFile::from_bytes_partial(&data),
+                Err(MidiError::Format { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn event_length_is_bounded_by_the_input() {
+        // One byte too many for what is left of the input
+        let mut events = vec![0x00, 0xFF, 0x01, 0x07, b'a', b'b'];
+        events.extend_from_slice(&END_OF_TRACK);
+        assert!(matches!(
+            MidiFile::from_bytes(&file_with_track(0, 96, &events)),
+            Err(MidiError::Format { .. })
+        ));
+    }
+}
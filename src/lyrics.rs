@@ -0,0 +1,112 @@
+use crate::{MetaEvent, MidiFile, MidiMessage};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+impl MidiFile {
+    /// Timed lyric syllables as (absolute tick, text), in timeline order.
+    ///
+    /// Lyrics meta events are used when the file has any; otherwise Text events are read
+    /// the way karaoke (.kar) files use them, skipping `@`-prefixed tag lines such as
+    /// `@T` (title) and `@L` (language). A `/` becomes a line break and a `\` a paragraph
+    /// break (a blank line).
+    pub fn lyrics(&self) -> Vec<(u64, String)> {
+        let has_lyrics = self.tracks.iter().any(|track| {
+            track
+                .events
+                .iter()
+                .any(|event| matches!(event.message, MidiMessage::Meta(MetaEvent::Lyrics(_))))
+        });
+
+        self.merged_events()
+            .filter_map(|(tick, _, event)| {
+                let text = match &event.message {
+                    MidiMessage::Meta(MetaEvent::Lyrics(text)) if has_lyrics => text.as_str(),
+                    MidiMessage::Meta(MetaEvent::Text(text))
+                        if !has_lyrics && !text.as_str().starts_with('@') =>
+                    {
+                        text.as_str()
+                    }
+                    _ => return None,
+                };
+                let syllable = text.replace('\\', "\n\n").replace('/', "\n");
+                (!syllable.is_empty()).then_some((tick, syllable))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MetaText, MidiHeader, MidiTrack};
+    use alloc::vec;
+
+    fn text(tick: u64, text: &str) -> (u64, MidiMessage) {
+        (
+            tick,
+            MidiMessage::Meta(MetaEvent::Text(MetaText::new(text))),
+        )
+    }
+
+    fn lyric(tick: u64, text: &str) -> (u64, MidiMessage) {
+        (
+            tick,
+            MidiMessage::Meta(MetaEvent::Lyrics(MetaText::new(text))),
+        )
+    }
+
+    fn file_with_tracks(tracks: Vec<Vec<(u64, MidiMessage)>>) -> MidiFile {
+        MidiFile {
+            header: MidiHeader {
+                format: 1,
+                num_tracks: tracks.len() as u16,
+                time_division: 96,
+            },
+            tracks: tracks.into_iter().map(MidiTrack::from_absolute).collect(),
+            chunks: Vec::new(),
+        }
+    }
+
+    fn owned(lyrics: &[(u64, &str)]) -> Vec<(u64, String)> {
+        lyrics
+            .iter()
+            .map(|&(tick, text)| (tick, String::from(text)))
+            .collect()
+    }
+
+    #[test]
+    fn karaoke_text_events_become_lyrics() {
+        let file = file_with_tracks(vec![
+            vec![text(0, "@KMIDI KARAOKE FILE")],
+            vec![
+                text(0, "@LENGL"),
+                text(0, "@TTwinkle"),
+                text(96, "\\Twin"),
+                text(144, "kle "),
+                text(192, "/lit"),
+                text(240, "tle "),
+            ],
+        ]);
+        assert_eq!(
+            file.lyrics(),
+            owned(&[
+                (96, "\n\nTwin"),
+                (144, "kle "),
+                (192, "\nlit"),
+                (240, "tle "),
+            ])
+        );
+    }
+
+    #[test]
+    fn lyrics_events_win_over_text() {
+        let file = file_with_tracks(vec![
+            vec![text(0, "Made with a sequencer"), lyric(10, "Hel")],
+            vec![lyric(5, "Oh "), lyric(20, "lo/")],
+        ]);
+        assert_eq!(
+            file.lyrics(),
+            owned(&[(5, "Oh "), (10, "Hel"), (20, "lo\n")])
+        );
+    }
+}
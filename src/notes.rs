@@ -0,0 +1,738 @@
+use crate::timing::pop_end_of_track;
+use crate::{Channel, MidiEvent, MidiFile, MidiMessage, MidiTrack};
+use alloc::collections::{BTreeMap, BTreeSet, VecDeque};
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+
+/// Release velocity for notes whose end gives none, the usual default for devices
+const DEFAULT_RELEASE_VELOCITY: u8 = 64;
+
+/// A sounding note reconstructed from a NoteOn and its matching NoteOff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Note {
+    pub channel: Channel,
+    pub note: u8,
+    pub velocity: u8,
+    pub release_velocity: u8, // From the NoteOff, or 64 when there is none or its velocity is 0
+    pub start_tick: u64,
+    pub end_tick: u64,
+}
+
+impl MidiFile {
+    /// Lazily iterate over the note events of every track, track by track, as
+    /// (track index, event)
+    pub fn note_events(&self) -> impl Iterator<Item = (usize, &MidiEvent)> + '_ {
+        self.tracks
+            .iter()
+            .enumerate()
+            .flat_map(|(index, track)| track.note_events().map(move |event| (index, event)))
+    }
+}
+
+impl MidiFile {
+    /// Greatest number of notes sounding at once across all tracks and channels
+    pub fn max_polyphony(&self) -> usize {
+        let notes: Vec<Note> = self.tracks.iter().flat_map(|track| track.notes()).collect();
+        peak_polyphony(&notes)
+    }
+
+    /// Greatest number of notes sounding at once on each zero-based channel
+    pub fn max_polyphony_by_channel(&self) -> [usize; 16] {
+        let mut by_channel: [Vec<Note>; 16] = Default::default();
+        for note in self.tracks.iter().flat_map(|track| track.notes()) {
+            by_channel[note.channel.get() as usize].push(note);
+        }
+        by_channel.map(|notes| peak_polyphony(&notes))
+    }
+}
+
+impl MidiTrack {
+    /// Lazily iterate over the NoteOn, NoteOff and PolyphonicKeyPressure events.
+    ///
+    /// Delta times are unchanged, so they are relative to the previous event of any kind.
+    pub fn note_events(&self) -> impl Iterator<Item = &MidiEvent> + '_ {
+        self.events.iter().filter(|event| {
+            matches!(
+                event.message,
+                MidiMessage::NoteOn { .. }
+                    | MidiMessage::NoteOff { .. }
+                    | MidiMessage::PolyphonicKeyPressure { .. }
+            )
+        })
+    }
+
+    /// Pair every NoteOn with its NoteOff (or NoteOn with velocity 0) into notes,
+    /// ordered by start tick, then channel and pitch.
+    ///
+    /// Overlapping notes on the same channel and pitch are matched first-on, first-off.
+    /// NoteOns still sounding when the track ends are closed at the track's final tick.
+    /// A note ended by a NoteOn with velocity 0, which parsing turns into a NoteOff with
+    /// velocity 0 unless `ParseOptions::preserve_note_on_zero` is set, gets release
+    /// velocity 64 either way, as does one ended by a NoteOff with velocity 0.
+    pub fn notes(&self) -> Vec<Note> {
+        let (mut notes, unclosed) = self.pair_notes();
+        notes.extend(unclosed);
+
+        // Unclosed notes were appended per (channel, note), so restore start order
+        notes.sort_by_key(|n| (n.start_tick, n.channel, n.note));
+        notes
+    }
+
+    /// Rewrite the track so every NoteOn is paired with exactly one NoteOff.
+    ///
+    /// A NoteOn for a (channel, note) that is already sounding is preceded by a NoteOff
+    /// for the earlier note at the same tick (retriggering it), a NoteOff for a note that
+    /// is not sounding is dropped, and notes still sounding at the end are released just
+    /// before the final EndOfTrack. Inserted NoteOffs use release velocity 64, and all
+    /// other events keep their absolute positions.
+    pub fn normalize_notes(&mut self) {
+        let mut active = BTreeSet::new();
+        let mut events = Vec::with_capacity(self.events.len());
+        let mut carried = 0u32; // Delta time of dropped events
+
+        for event in self.events.drain(..) {
+            let delta_time = event.delta_time.saturating_add(carried);
+            carried = 0;
+            match event.message {
+                MidiMessage::NoteOn {
+                    channel,
+                    note,
+                    velocity,
+                } if velocity > 0 => {
+                    if active.insert((channel, note)) {
+                        events.push(MidiEvent {
+                            delta_time,
+                            ..event
+                        });
+                    } else {
+                        events.push(note_off(delta_time, channel, note));
+                        events.push(MidiEvent {
+                            delta_time: 0,
+                            ..event
+                        });
+                    }
+                }
+                MidiMessage::NoteOn { channel, note, .. }
+                | MidiMessage::NoteOff { channel, note, .. } => {
+                    if active.remove(&(channel, note)) {
+                        events.push(MidiEvent {
+                            delta_time,
+                            ..event
+                        });
+                    } else {
+                        carried = delta_time;
+                    }
+                }
+                _ => events.push(MidiEvent {
+                    delta_time,
+                    ..event
+                }),
+            }
+        }
+
+        // Release anything still held, keeping a trailing EndOfTrack last
+        let end_of_track = events.pop_if(|event| event.message.is_end_of_track());
+        let mut delta_time = carried;
+        if let Some(end_of_track) = &end_of_track {
+            delta_time = delta_time.saturating_add(end_of_track.delta_time);
+        }
+        for (channel, note) in active {
+            events.push(note_off(delta_time, channel, note));
+            delta_time = 0;
+        }
+        if let Some(end_of_track) = end_of_track {
+            events.push(MidiEvent {
+                delta_time,
+                ..end_of_track
+            });
+        }
+
+        self.events = events;
+    }
+
+    /// Fold the sustain pedal (CC64) into note lengths: a NoteOff that arrives while its
+    /// channel's pedal is down is moved to the pedal release, and the CC64 events removed.
+    ///
+    /// Pedal state is tracked per channel, with values of 64 and up meaning down, so a
+    /// repeated press before a release changes nothing. Striking a sustained note again
+    /// ends the sustained copy first, and notes still sustained when the track ends are
+    /// released just before the final EndOfTrack.
+    pub fn apply_sustain(&mut self) {
+        let mut pedal_down = [false; 16];
+        let mut sustained: Vec<MidiMessage> = Vec::new(); // Deferred NoteOffs, in order
+        let mut events = Vec::with_capacity(self.events.len());
+
+        for (tick, event) in self.iter_absolute() {
+            let message = &event.message;
+            match *message {
+                MidiMessage::ControlChange {
+                    channel,
+                    controller: 64,
+                    value,
+                } => {
+                    let down = value >= 64;
+                    if pedal_down[channel.get() as usize] && !down {
+                        sustained.retain(|off| {
+                            let release = off.channel() == Some(channel);
+                            if release {
+                                events.push((tick, off.clone()));
+                            }
+                            !release
+                        });
+                    }
+                    pedal_down[channel.get() as usize] = down;
+                }
+                MidiMessage::NoteOn { channel, note, .. } if message.is_note_on() => {
+                    // Restriking a sustained note cuts off the ringing one
+                    sustained.retain(|off| {
+                        let release = note_key(off) == Some((channel, note));
+                        if release {
+                            events.push((tick, off.clone()));
+                        }
+                        !release
+                    });
+                    events.push((tick, message.clone()));
+                }
+                MidiMessage::NoteOn { channel, .. } | MidiMessage::NoteOff { channel, .. }
+                    if pedal_down[channel.get() as usize] =>
+                {
+                    sustained.push(message.clone());
+                }
+                _ => events.push((tick, message.clone())),
+            }
+        }
+
+        // Release anything still sustained, keeping a trailing EndOfTrack last
+        let end_of_track = pop_end_of_track(&mut events);
+        let end_tick = self.absolute_times().last().copied().unwrap_or(0);
+        events.extend(sustained.into_iter().map(|off| (end_tick, off)));
+        events.extend(end_of_track);
+
+        *self = MidiTrack::from_absolute(events);
+    }
+
+    /// Reduce the track to a single melodic line: at every instant only the highest
+    /// sounding note is kept, and all other events stay where they are.
+    ///
+    /// A note covered by a higher one is cut off when the higher note starts and, if it is
+    /// still held when that note ends, sounds again from there. When sounding notes share
+    /// the top pitch, the one that started first wins, then the lower channel. Notes are
+    /// paired as by `notes`, zero-length notes are dropped and the NoteOffs written keep
+    /// each note's release velocity.
+    pub fn extract_melody(&self) -> MidiTrack {
+        let notes = self.notes();
+        // Orders sounding notes so the melody note comes first
+        let rank = |index: usize| {
+            let note = &notes[index];
+            (Reverse(note.note), note.start_tick, note.channel, index)
+        };
+
+        // Notes ending and starting at each tick where the sounding set changes
+        let mut changes: BTreeMap<u64, (Vec<usize>, Vec<usize>)> = BTreeMap::new();
+        for (index, note) in notes.iter().enumerate() {
+            if note.start_tick < note.end_tick {
+                changes.entry(note.end_tick).or_default().0.push(index);
+                changes.entry(note.start_tick).or_default().1.push(index);
+            }
+        }
+
+        let mut sounding = BTreeSet::new();
+        let mut current = None;
+        let mut melody = Vec::new();
+        for (tick, (ending, starting)) in changes {
+            for index in ending {
+                sounding.remove(&rank(index));
+            }
+            for index in starting {
+                sounding.insert(rank(index));
+            }
+
+            let top = sounding.first().map(|&(.., index)| index);
+            if top != current {
+                if let Some(index) = current {
+                    let note = &notes[index];
+                    melody.push((
+                        tick,
+                        MidiMessage::NoteOff {
+                            channel: note.channel,
+                            note: note.note,
+                            velocity: note.release_velocity,
+                        },
+                    ));
+                }
+                if let Some(index) = top {
+                    let note = &notes[index];
+                    melody.push((
+                        tick,
+                        MidiMessage::NoteOn {
+                            channel: note.channel,
+                            note: note.note,
+                            velocity: note.velocity,
+                        },
+                    ));
+                }
+                current = top;
+            }
+        }
+
+        let mut events: Vec<(u64, MidiMessage)> = self
+            .iter_absolute()
+            .filter(|(_, event)| note_key(&event.message).is_none())
+            .map(|(tick, event)| (tick, event.message.clone()))
+            .collect();
+        let end_of_track = pop_end_of_track(&mut events);
+
+        // Stable, so other events come before notes on the same tick
+        events.extend(melody);
+        events.sort_by_key(|(tick, _)| *tick);
+        events.extend(end_of_track);
+        MidiTrack::from_absolute(events)
+    }
+
+    /// NoteOns that are never followed by a matching NoteOff, closed at the final tick
+    pub(crate) fn unclosed_notes(&self) -> Vec<Note> {
+        let (_, mut unclosed) = self.pair_notes();
+        unclosed.sort_by_key(|n| (n.start_tick, n.channel, n.note));
+        unclosed
+    }
+
+    /// Walk the track matching note starts to note ends, returning the closed notes and
+    /// the notes still sounding at the end of the track
+    fn pair_notes(&self) -> (Vec<Note>, Vec<Note>) {
+        let mut active: BTreeMap<(Channel, u8), VecDeque<(u64, u8)>> = BTreeMap::new();
+        let mut notes = Vec::new();
+        let mut last_tick = 0;
+
+        for (tick, event) in self.iter_absolute() {
+            last_tick = tick;
+            match event.message {
+                MidiMessage::NoteOn {
+                    channel,
+                    note,
+                    velocity,
+                } if velocity > 0 => {
+                    active
+                        .entry((channel, note))
+                        .or_default()
+                        .push_back((tick, velocity));
+                }
+                MidiMessage::NoteOn { channel, note, .. }
+                | MidiMessage::NoteOff { channel, note, .. } => {
+                    // A NoteOn with velocity 0, also when parsed as a NoteOff with
+                    // velocity 0, carries no release velocity
+                    let release_velocity = match event.message {
+                        MidiMessage::NoteOff { velocity, .. } if velocity > 0 => velocity,
+                        _ => DEFAULT_RELEASE_VELOCITY,
+                    };
+                    // A NoteOff without a sounding note is ignored
+                    if let Some((start_tick, velocity)) = active
+                        .get_mut(&(channel, note))
+                        .and_then(|starts| starts.pop_front())
+                    {
+                        notes.push(Note {
+                            channel,
+                            note,
+                            velocity,
+                            release_velocity,
+                            start_tick,
+                            end_tick: tick,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Close anything left hanging at the end of the track
+        let mut unclosed = Vec::new();
+        for ((channel, note), starts) in active {
+            for (start_tick, velocity) in starts {
+                unclosed.push(Note {
+                    channel,
+                    note,
+                    velocity,
+                    release_velocity: DEFAULT_RELEASE_VELOCITY,
+                    start_tick,
+                    end_tick: last_tick,
+                });
+            }
+        }
+
+        (notes, unclosed)
+    }
+}
+
+/// Most notes sounding at the same time. A note ending on the tick another starts does
+/// not overlap it, and unmatched NoteOffs never reach here, so the count cannot underflow.
+fn peak_polyphony(notes: &[Note]) -> usize {
+    // Ends sort before starts on the same tick
+    let mut changes: Vec<(u64, bool)> = notes
+        .iter()
+        .flat_map(|n| [(n.start_tick, true), (n.end_tick, false)])
+        .collect();
+    changes.sort();
+
+    let (mut sounding, mut peak) = (0usize, 0);
+    for (_, starts) in changes {
+        if starts {
+            sounding += 1;
+            peak = peak.max(sounding);
+        } else {
+            sounding = sounding.saturating_sub(1);
+        }
+    }
+    peak
+}
+
+/// (channel, note) of a note message
+fn note_key(message: &MidiMessage) -> Option<(Channel, u8)> {
+    match *message {
+        MidiMessage::NoteOn { channel, note, .. } | MidiMessage::NoteOff { channel, note, .. } => {
+            Some((channel, note))
+        }
+        _ => None,
+    }
+}
+
+/// NoteOff with the default release velocity
+fn note_off(delta_time: u32, channel: Channel, note: u8) -> MidiEvent {
+    MidiEvent {
+        delta_time,
+        message: MidiMessage::NoteOff {
+            channel,
+            note,
+            velocity: DEFAULT_RELEASE_VELOCITY,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MetaEvent;
+    use alloc::vec;
+
+    fn on(channel: u8, note: u8, velocity: u8) -> MidiMessage {
+        MidiMessage::NoteOn {
+            channel: Channel::new(channel).unwrap(),
+            note,
+            velocity,
+        }
+    }
+
+    fn off(channel: u8, note: u8, velocity: u8) -> MidiMessage {
+        MidiMessage::NoteOff {
+            channel: Channel::new(channel).unwrap(),
+            note,
+            velocity,
+        }
+    }
+
+    const END_OF_TRACK: MidiMessage = MidiMessage::Meta(MetaEvent::EndOfTrack);
+
+    /// (channel, note, start tick, end tick) of each note in the track
+    fn spans(track: &MidiTrack) -> Vec<(u8, u8, u64, u64)> {
+        track
+            .notes()
+            .iter()
+            .map(|n| (n.channel.get(), n.note, n.start_tick, n.end_tick))
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_notes_on_one_pitch_pair_first_on_first_off() {
+        let track = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (10, on(0, 60, 90)),
+            (20, off(0, 60, 0)),
+            (30, off(0, 60, 0)),
+            (30, END_OF_TRACK),
+        ]);
+        let notes = track.notes();
+        assert_eq!(spans(&track), [(0, 60, 0, 20), (0, 60, 10, 30)]);
+        assert_eq!(notes[0].velocity, 100);
+        assert_eq!(notes[1].velocity, 90);
+    }
+
+    #[test]
+    fn notes_pair_per_channel_and_pitch() {
+        let track = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (0, on(1, 60, 100)),
+            (5, on(0, 64, 100)),
+            (10, off(1, 60, 0)),
+            // A NoteOn with velocity 0 ends a note too
+            (15, on(0, 64, 0)),
+            (20, off(0, 60, 0)),
+            (20, END_OF_TRACK),
+        ]);
+        assert_eq!(
+            spans(&track),
+            [(0, 60, 0, 20), (1, 60, 0, 10), (0, 64, 5, 15)]
+        );
+    }
+
+    #[test]
+    fn unmatched_notes_close_at_the_final_tick() {
+        let track = MidiTrack::from_absolute([
+            (0, off(0, 62, 0)),
+            (0, on(0, 60, 100)),
+            (10, on(0, 64, 100)),
+            (40, END_OF_TRACK),
+        ]);
+        assert_eq!(spans(&track), [(0, 60, 0, 40), (0, 64, 10, 40)]);
+    }
+
+    /// Messages of a track paired with their absolute ticks
+    fn absolute(track: &MidiTrack) -> Vec<(u64, MidiMessage)> {
+        track
+            .iter_absolute()
+            .map(|(tick, event)| (tick, event.message.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn normalize_notes_balances_stuck_notes() {
+        let mut track = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (10, on(0, 60, 90)),
+            (20, on(0, 64, 80)),
+            (30, off(0, 60, 40)),
+            (35, off(0, 67, 40)),
+            (50, END_OF_TRACK),
+        ]);
+        track.normalize_notes();
+        assert_eq!(
+            absolute(&track),
+            [
+                (0, on(0, 60, 100)),
+                // The retriggered note ends the first one
+                (10, off(0, 60, 64)),
+                (10, on(0, 60, 90)),
+                (20, on(0, 64, 80)),
+                (30, off(0, 60, 40)),
+                // The unmatched NoteOff at 35 is gone and the held note released
+                (50, off(0, 64, 64)),
+                (50, END_OF_TRACK),
+            ]
+        );
+
+        let ons = track
+            .events
+            .iter()
+            .filter(|e| e.message.is_note_on())
+            .count();
+        let offs = track
+            .events
+            .iter()
+            .filter(|e| matches!(e.message, MidiMessage::NoteOff { .. }))
+            .count();
+        assert_eq!((ons, offs), (3, 3));
+    }
+
+    #[test]
+    fn normalize_notes_leaves_paired_notes_alone() {
+        let track = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (0, on(1, 60, 100)),
+            (10, off(0, 60, 0)),
+            (20, off(1, 60, 0)),
+            (20, END_OF_TRACK),
+        ]);
+        let mut normalized = track.clone();
+        normalized.normalize_notes();
+        assert_eq!(normalized, track);
+    }
+
+    fn file_with_tracks(tracks: Vec<Vec<(u64, MidiMessage)>>) -> MidiFile {
+        MidiFile {
+            header: crate::MidiHeader {
+                format: 1,
+                num_tracks: tracks.len() as u16,
+                time_division: 96,
+            },
+            tracks: tracks.into_iter().map(MidiTrack::from_absolute).collect(),
+            chunks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn three_overlapping_notes_give_polyphony_3() {
+        let file = file_with_tracks(vec![
+            vec![
+                (0, on(0, 60, 100)),
+                (10, on(0, 64, 100)),
+                (30, off(0, 60, 0)),
+                (40, off(0, 64, 0)),
+            ],
+            vec![(20, on(1, 67, 100)), (35, off(1, 67, 0))],
+        ]);
+        assert_eq!(file.max_polyphony(), 3);
+
+        let mut expected = [0; 16];
+        expected[0] = 2;
+        expected[1] = 1;
+        assert_eq!(file.max_polyphony_by_channel(), expected);
+    }
+
+    #[test]
+    fn stray_note_offs_and_back_to_back_notes_do_not_overlap() {
+        let file = file_with_tracks(vec![vec![
+            (0, off(0, 50, 0)),
+            (0, off(0, 51, 0)),
+            (0, on(0, 60, 100)),
+            (10, off(0, 60, 0)),
+            (10, on(0, 62, 100)),
+            (20, off(0, 62, 0)),
+        ]]);
+        assert_eq!(file.max_polyphony(), 1);
+        assert_eq!(file_with_tracks(vec![]).max_polyphony(), 0);
+    }
+
+    fn pedal(channel: u8, value: u8) -> MidiMessage {
+        MidiMessage::ControlChange {
+            channel: Channel::new(channel).unwrap(),
+            controller: 64,
+            value,
+        }
+    }
+
+    #[test]
+    fn held_pedal_lengthens_notes_to_its_release() {
+        let mut track = MidiTrack::from_absolute([
+            (0, pedal(0, 127)),
+            (0, on(0, 60, 100)),
+            (10, off(0, 60, 0)),
+            (10, on(0, 64, 100)),
+            (20, off(0, 64, 0)),
+            // A second press while down changes nothing
+            (25, pedal(0, 100)),
+            (30, on(0, 67, 100)),
+            (40, off(0, 67, 0)),
+            (60, pedal(0, 0)),
+            (70, END_OF_TRACK),
+        ]);
+        track.apply_sustain();
+        assert_eq!(
+            spans(&track),
+            [(0, 60, 0, 60), (0, 64, 10, 60), (0, 67, 30, 60)]
+        );
+        assert!(!track
+            .events
+            .iter()
+            .any(|e| matches!(e.message, MidiMessage::ControlChange { .. })));
+        assert_eq!(track.absolute_times().last(), Some(&70));
+    }
+
+    #[test]
+    fn pedal_is_tracked_per_channel() {
+        let mut track = MidiTrack::from_absolute([
+            (0, pedal(1, 127)),
+            (0, on(0, 60, 100)),
+            (0, on(1, 60, 100)),
+            (10, off(0, 60, 0)),
+            (10, off(1, 60, 0)),
+            (50, pedal(1, 0)),
+        ]);
+        track.apply_sustain();
+        assert_eq!(spans(&track), [(0, 60, 0, 10), (1, 60, 0, 50)]);
+    }
+
+    #[test]
+    fn restruck_and_unreleased_notes_end_early_and_at_the_end() {
+        let mut track = MidiTrack::from_absolute([
+            (0, pedal(0, 127)),
+            (0, on(0, 60, 100)),
+            (10, off(0, 60, 0)),
+            // Striking the sustained note again cuts the first one off
+            (20, on(0, 60, 90)),
+            (30, off(0, 60, 0)),
+            (80, END_OF_TRACK),
+        ]);
+        track.apply_sustain();
+        assert_eq!(spans(&track), [(0, 60, 0, 20), (0, 60, 20, 80)]);
+        assert_eq!(track.events.last().unwrap().message, END_OF_TRACK);
+    }
+
+    #[test]
+    fn chords_reduce_to_their_top_notes() {
+        let track = MidiTrack::from_absolute([
+            (0, MidiMessage::Meta(MetaEvent::SetTempo(500_000))),
+            (0, on(0, 60, 80)),
+            (0, on(0, 64, 80)),
+            (0, on(0, 67, 100)),
+            (96, off(0, 60, 0)),
+            (96, off(0, 64, 0)),
+            (96, off(0, 67, 50)),
+            (96, on(0, 65, 80)),
+            (96, on(0, 69, 90)),
+            (192, off(0, 65, 0)),
+            (192, off(0, 69, 0)),
+            (200, END_OF_TRACK),
+        ]);
+        let melody = track.extract_melody();
+        assert_eq!(
+            absolute(&melody),
+            [
+                (0, MidiMessage::Meta(MetaEvent::SetTempo(500_000))),
+                (0, on(0, 67, 100)),
+                (96, off(0, 67, 50)),
+                (96, on(0, 69, 90)),
+                (192, off(0, 69, 64)),
+                (200, END_OF_TRACK),
+            ]
+        );
+        assert_eq!(peak_polyphony(&melody.notes()), 1);
+    }
+
+    #[test]
+    fn covered_note_sounds_again_when_uncovered() {
+        let track = MidiTrack::from_absolute([
+            (0, on(0, 60, 80)),
+            (10, on(0, 72, 90)),
+            (20, off(0, 72, 0)),
+            (30, off(0, 60, 0)),
+        ]);
+        assert_eq!(
+            spans(&track.extract_melody()),
+            [(0, 60, 0, 10), (0, 72, 10, 20), (0, 60, 20, 30)]
+        );
+    }
+
+    #[test]
+    fn shared_top_pitch_goes_to_the_earlier_note_then_lower_channel() {
+        let track = MidiTrack::from_absolute([
+            (0, on(3, 72, 80)),
+            (0, on(2, 72, 80)),
+            (5, on(1, 72, 80)),
+            (10, off(2, 72, 0)),
+            (20, off(3, 72, 0)),
+            (30, off(1, 72, 0)),
+        ]);
+        assert_eq!(
+            spans(&track.extract_melody()),
+            [(2, 72, 0, 10), (3, 72, 10, 20), (1, 72, 20, 30)]
+        );
+    }
+
+    #[test]
+    fn release_velocity_comes_from_the_note_off() {
+        let track = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (0, on(0, 62, 100)),
+            (0, on(0, 64, 100)),
+            (0, on(0, 65, 100)),
+            (10, off(0, 60, 23)),
+            (20, on(0, 62, 0)),
+            (30, off(0, 64, 0)),
+            (40, END_OF_TRACK),
+        ]);
+        let releases: Vec<(u8, u8)> = track
+            .notes()
+            .iter()
+            .map(|n| (n.note, n.release_velocity))
+            .collect();
+        // Ends that carry no release velocity, and the unclosed note, get 64
+        assert_eq!(releases, [(60, 23), (62, 64), (64, 64), (65, 64)]);
+    }
+}
@@ -0,0 +1,57 @@
+use alloc::format;
+use alloc::string::String;
+
+/// Octave number given to middle C (note 60) by the common convention, where 60 is "C4"
+pub const MIDDLE_C_OCTAVE: i8 = 4;
+
+/// Pitch class names within an octave, spelled with sharps
+const PITCH_CLASSES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// Name of a MIDI note number with its octave, where note 60 is "C4"
+pub fn note_name(note: u8) -> String {
+    note_name_with_middle_c(note, MIDDLE_C_OCTAVE)
+}
+
+/// Name of a MIDI note number, numbering octaves so that note 60 is in `middle_c_octave`.
+///
+/// Pass 3 for the Yamaha convention where note 60 is "C3".
+pub fn note_name_with_middle_c(note: u8, middle_c_octave: i8) -> String {
+    let octave = note as i32 / 12 - 5 + middle_c_octave as i32;
+    format!("{}{}", PITCH_CLASSES[(note % 12) as usize], octave)
+}
+
+/// Note number for a name like "C4", "F#5", "Bb3" or "c-1", where "C4" is note 60
+pub fn note_from_name(name: &str) -> Option<u8> {
+    note_from_name_with_middle_c(name, MIDDLE_C_OCTAVE)
+}
+
+/// Note number for a name, with octaves numbered so that note 60 is in `middle_c_octave`.
+///
+/// Accepts a letter A-G in either case, any number of `#` or `b` accidentals and a
+/// possibly negative octave. Returns None if the name is malformed or out of range.
+pub fn note_from_name_with_middle_c(name: &str, middle_c_octave: i8) -> Option<u8> {
+    let name = name.trim();
+    let mut chars = name.chars();
+    let mut pitch_class: i32 = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+
+    let rest = chars.as_str();
+    let octave_start = rest.find(|c| c != '#' && c != 'b').unwrap_or(rest.len());
+    for accidental in rest[..octave_start].chars() {
+        pitch_class += if accidental == '#' { 1 } else { -1 };
+    }
+
+    let octave: i16 = rest[octave_start..].parse().ok()?;
+    let note = (octave as i32 + 5 - middle_c_octave as i32) * 12 + pitch_class;
+    u8::try_from(note).ok().filter(|&note| note <= 127)
+}
@@ -0,0 +1,89 @@
+use crate::MidiFile;
+use midir::MidiOutputConnection;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Controller that silences every note on a channel, sent when playback is stopped
+const ALL_NOTES_OFF: u8 = 123;
+
+/// A file playing on a background thread, returned by `MidiFile::play`
+#[derive(Debug)]
+pub struct Playback {
+    stop: Sender<()>,
+    thread: JoinHandle<MidiOutputConnection>,
+}
+
+impl Playback {
+    /// Interrupt playback, silence all channels and get the output connection back
+    pub fn stop(self) -> MidiOutputConnection {
+        // The thread may already have finished, in which case nothing is listening
+        let _ = self.stop.send(());
+        self.join()
+    }
+
+    /// Wait for playback to reach the end of the file and get the connection back
+    pub fn join(self) -> MidiOutputConnection {
+        self.thread
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    }
+
+    /// True once every event has been sent or playback has been stopped
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+}
+
+impl MidiFile {
+    /// Play the file to a MIDI output port on a new thread, timing events with the tempo
+    /// map so SetTempo changes take effect.
+    ///
+    /// Only channel messages are sent; meta events and SysEx are skipped, as are messages
+    /// that cannot be encoded (a data value above 127) and events whose time cannot be
+    /// computed, such as in a file with time division 0. Use the returned handle to stop
+    /// playback early or wait for it to finish. A failed send ends playback.
+    pub fn play(&self, port: MidiOutputConnection) -> Playback {
+        let tempo_map = self.tempo_map();
+        let schedule: Vec<(Duration, Vec<u8>)> = self
+            .merged_events()
+            .filter(|(_, _, event)| event.message.is_channel_message())
+            .filter_map(|(tick, _, event)| {
+                // A zero time division gives times that are not finite; skip those events
+                let seconds = tempo_map.ticks_to_seconds(tick).max(0.0);
+                let offset = Duration::try_from_secs_f64(seconds).ok()?;
+                Some((offset, event.message.to_bytes().ok()?))
+            })
+            .collect();
+
+        let (stop, stopped) = mpsc::channel();
+        let thread = thread::spawn(move || {
+            let mut port = port;
+            let start = Instant::now();
+            for (offset, bytes) in schedule {
+                let wait = (start + offset).saturating_duration_since(Instant::now());
+                match stopped.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => thread::sleep(wait),
+                    Ok(()) => {
+                        silence(&mut port);
+                        break;
+                    }
+                }
+                if port.send(&bytes).is_err() {
+                    break;
+                }
+            }
+            port
+        });
+
+        Playback { stop, thread }
+    }
+}
+
+/// Send All Notes Off on every channel so nothing is left sounding
+fn silence(port: &mut MidiOutputConnection) {
+    for channel in 0..16 {
+        let _ = port.send(&[0xB0 | channel, ALL_NOTES_OFF, 0]);
+    }
+}
@@ -0,0 +1,15 @@
+//! The types most programs need, for a single glob import.
+//!
+//! `use midi_oxidizer::prelude::*;` brings in the file model (`MidiFile`, `MidiHeader`,
+//! `MidiTrack`, `MidiEvent`, `MidiMessage`, `MetaEvent`, `Channel`, `TimeDivision`),
+//! `MidiError`, `ParseOptions`, `WriteOptions` (with `std`), `MidiBuilder`, `Note`,
+//! `TempoMap` and the note name and BPM conversion functions.
+
+pub use crate::{
+    bpm_from_tempo, note_from_name, note_name, tempo_from_bpm, Channel, MetaEvent, MidiBuilder,
+    MidiError, MidiEvent, MidiFile, MidiHeader, MidiMessage, MidiTrack, Note, ParseOptions,
+    TempoMap, TimeDivision,
+};
+
+#[cfg(feature = "std")]
+pub use crate::WriteOptions;
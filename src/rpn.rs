@@ -0,0 +1,241 @@
+use crate::{Channel, MidiMessage};
+
+/// Controller numbers used to select and set registered/non-registered parameters
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+const NRPN_LSB: u8 = 98;
+const NRPN_MSB: u8 = 99;
+const RPN_LSB: u8 = 100;
+const RPN_MSB: u8 = 101;
+
+/// Parameter number that deselects any parameter (both bytes 127)
+const NULL_PARAMETER: u16 = 0x3FFF;
+
+/// A parameter change reassembled from a Control Change sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpnEvent {
+    Registered {
+        channel: Channel,
+        param: u16,
+        value: u16,
+    },
+    NonRegistered {
+        channel: Channel,
+        param: u16,
+        value: u16,
+    },
+}
+
+/// Which kind of parameter data entry currently applies to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Selection {
+    #[default]
+    None,
+    Registered,
+    NonRegistered,
+}
+
+/// Parameter selection and data entry progress for one channel
+#[derive(Debug, Clone, Copy, Default)]
+struct ChannelState {
+    rpn_msb: Option<u8>,
+    rpn_lsb: Option<u8>,
+    nrpn_msb: Option<u8>,
+    nrpn_lsb: Option<u8>,
+    selection: Selection,
+    data_msb: Option<u8>,
+}
+
+impl ChannelState {
+    /// The selected 14-bit parameter number, once both halves are known
+    fn parameter(msb: Option<u8>, lsb: Option<u8>) -> Option<u16> {
+        Some(((msb? as u16) << 7) | lsb? as u16)
+    }
+
+    /// The change setting the selected parameter to `value`, if one is selected
+    fn event(&self, channel: Channel, value: u16) -> Option<RpnEvent> {
+        let (msb, lsb) = match self.selection {
+            Selection::None => return None,
+            Selection::Registered => (self.rpn_msb, self.rpn_lsb),
+            Selection::NonRegistered => (self.nrpn_msb, self.nrpn_lsb),
+        };
+        let param = Self::parameter(msb, lsb).filter(|&param| param != NULL_PARAMETER)?;
+
+        Some(if self.selection == Selection::Registered {
+            RpnEvent::Registered {
+                channel,
+                param,
+                value,
+            }
+        } else {
+            RpnEvent::NonRegistered {
+                channel,
+                param,
+                value,
+            }
+        })
+    }
+}
+
+/// Reassembles RPN and NRPN changes from Control Change events, tracking each channel
+/// separately.
+///
+/// An event is emitted on every Data Entry MSB (CC 6) for a selected parameter, with
+/// the value `msb << 7`, since the LSB is optional. A Data Entry LSB (CC 38) after it
+/// refines the value to `(msb << 7) | lsb` and emits the change again.
+#[derive(Debug, Clone, Default)]
+pub struct RpnDecoder {
+    channels: [ChannelState; 16],
+}
+
+impl RpnDecoder {
+    /// Decoder with no parameter selected on any channel
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a message, returning a parameter change if it completes one.
+    /// Anything other than a Control Change is ignored.
+    pub fn feed(&mut self, message: &MidiMessage) -> Option<RpnEvent> {
+        let MidiMessage::ControlChange {
+            channel,
+            controller,
+            value,
+        } = *message
+        else {
+            return None;
+        };
+        let state = &mut self.channels[channel.get() as usize];
+
+        match controller {
+            RPN_MSB | RPN_LSB => {
+                if controller == RPN_MSB {
+                    state.rpn_msb = Some(value);
+                } else {
+                    state.rpn_lsb = Some(value);
+                }
+                state.selection = Selection::Registered;
+                state.data_msb = None;
+                None
+            }
+            NRPN_MSB | NRPN_LSB => {
+                if controller == NRPN_MSB {
+                    state.nrpn_msb = Some(value);
+                } else {
+                    state.nrpn_lsb = Some(value);
+                }
+                state.selection = Selection::NonRegistered;
+                state.data_msb = None;
+                None
+            }
+            DATA_ENTRY_MSB => {
+                // The LSB is optional, so the MSB alone sets the value
+                state.data_msb = Some(value);
+                state.event(channel, (value as u16) << 7)
+            }
+            DATA_ENTRY_LSB => {
+                let data_value = ((state.data_msb? as u16) << 7) | value as u16;
+                state.event(channel, data_value)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn cc(channel: u8, controller: u8, value: u8) -> MidiMessage {
+        MidiMessage::ControlChange {
+            channel: Channel::new(channel).unwrap(),
+            controller,
+            value,
+        }
+    }
+
+    fn decode(decoder: &mut RpnDecoder, messages: &[MidiMessage]) -> Vec<RpnEvent> {
+        messages.iter().filter_map(|m| decoder.feed(m)).collect()
+    }
+
+    #[test]
+    fn pitch_bend_range_sequence_gives_one_event() {
+        let mut decoder = RpnDecoder::new();
+        let events = decode(&mut decoder, &[cc(0, 101, 0), cc(0, 100, 0), cc(0, 6, 2)]);
+        assert_eq!(
+            events,
+            [RpnEvent::Registered {
+                channel: Channel::new(0).unwrap(),
+                param: 0,
+                value: 2 << 7,
+            }]
+        );
+    }
+
+    #[test]
+    fn data_entry_lsb_refines_the_value() {
+        let channel = Channel::new(3).unwrap();
+        let mut decoder = RpnDecoder::new();
+        let events = decode(
+            &mut decoder,
+            &[cc(3, 99, 1), cc(3, 98, 8), cc(3, 6, 64), cc(3, 38, 5)],
+        );
+        assert_eq!(
+            events,
+            [
+                RpnEvent::NonRegistered {
+                    channel,
+                    param: (1 << 7) | 8,
+                    value: 64 << 7,
+                },
+                RpnEvent::NonRegistered {
+                    channel,
+                    param: (1 << 7) | 8,
+                    value: (64 << 7) | 5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn channels_are_tracked_separately() {
+        let mut decoder = RpnDecoder::new();
+        let events = decode(
+            &mut decoder,
+            &[
+                cc(0, 101, 0),
+                cc(1, 101, 0),
+                cc(0, 100, 1),
+                // Channel 1 never selects its LSB, so its data entry is ignored
+                cc(1, 6, 10),
+                cc(0, 6, 20),
+            ],
+        );
+        assert_eq!(
+            events,
+            [RpnEvent::Registered {
+                channel: Channel::new(0).unwrap(),
+                param: 1,
+                value: 20 << 7,
+            }]
+        );
+    }
+
+    #[test]
+    fn null_parameter_ignores_data_entry() {
+        let mut decoder = RpnDecoder::new();
+        let events = decode(
+            &mut decoder,
+            &[
+                cc(0, 101, 0),
+                cc(0, 100, 0),
+                cc(0, 101, 127),
+                cc(0, 100, 127),
+                cc(0, 6, 2),
+                cc(0, 38, 0),
+            ],
+        );
+        assert!(events.is_empty());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::MidiError;
+#[cfg(feature = "std")]
+use byteorder::ReadBytesExt;
+#[cfg(feature = "std")]
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+
+/// Message of the Format error a slice reports when read past its end without std
+#[cfg(not(feature = "std"))]
+pub(crate) const UNEXPECTED_EOF: &str = "Unexpected end of data";
+
+/// Byte input the parser runs over
+pub(crate) trait MidiSource {
+    /// Read a single byte
+    fn read_u8(&mut self) -> Result<u8, MidiError>;
+
+    /// Fill the buffer completely
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), MidiError>;
+
+    /// Current offset from the start of the input
+    fn position(&mut self) -> Result<u64, MidiError>;
+
+    /// Move to an absolute offset from the start of the input
+    fn seek_to(&mut self, pos: u64) -> Result<(), MidiError>;
+
+    /// Step back one byte so it is read again
+    fn unread_byte(&mut self) -> Result<(), MidiError>;
+
+    /// Total length of the input in bytes
+    fn total_len(&mut self) -> Result<u64, MidiError>;
+
+    /// Read a big-endian u16
+    fn read_u16_be(&mut self) -> Result<u16, MidiError> {
+        let mut buf = [0u8; 2];
+        self.read_exact(&mut buf)?;
+        Ok(u16::from_be_bytes(buf))
+    }
+
+    /// Read a big-endian u32
+    fn read_u32_be(&mut self) -> Result<u32, MidiError> {
+        let mut buf = [0u8; 4];
+        self.read_exact(&mut buf)?;
+        Ok(u32::from_be_bytes(buf))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> MidiSource for R {
+    fn read_u8(&mut self) -> Result<u8, MidiError> {
+        Ok(ReadBytesExt::read_u8(self)?)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), MidiError> {
+        Ok(Read::read_exact(self, buf)?)
+    }
+
+    fn position(&mut self) -> Result<u64, MidiError> {
+        Ok(self.stream_position()?)
+    }
+
+    fn seek_to(&mut self, pos: u64) -> Result<(), MidiError> {
+        self.seek(SeekFrom::Start(pos))?;
+        Ok(())
+    }
+
+    fn unread_byte(&mut self) -> Result<(), MidiError> {
+        // Relative, so a BufReader steps back within its buffer instead of discarding it
+        self.seek_relative(-1)?;
+        Ok(())
+    }
+
+    fn total_len(&mut self) -> Result<u64, MidiError> {
+        let pos = self.stream_position()?;
+        let len = self.seek(SeekFrom::End(0))?;
+        if pos != len {
+            self.seek(SeekFrom::Start(pos))?;
+        }
+        Ok(len)
+    }
+}
+
+/// Buffered input that keeps count of its own position and length, since asking a
+/// file for them costs a system call and the parser asks on every event
+#[cfg(feature = "std")]
+pub(crate) struct BufferedSource<R> {
+    reader: BufReader<R>,
+    pos: u64,
+    len: Option<u64>, // Measured on first use
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> BufferedSource<R> {
+    /// Buffer `reader`, starting from its current position
+    pub(crate) fn new(mut reader: R) -> Result<Self, MidiError> {
+        let pos = reader.stream_position()?;
+        Ok(BufferedSource {
+            reader: BufReader::new(reader),
+            pos,
+            len: None,
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> MidiSource for BufferedSource<R> {
+    fn read_u8(&mut self) -> Result<u8, MidiError> {
+        let byte = ReadBytesExt::read_u8(&mut self.reader)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), MidiError> {
+        Read::read_exact(&mut self.reader, buf)?;
+        self.pos += buf.len() as u64;
+        Ok(())
+    }
+
+    fn position(&mut self) -> Result<u64, MidiError> {
+        Ok(self.pos)
+    }
+
+    fn seek_to(&mut self, pos: u64) -> Result<(), MidiError> {
+        // Relative seeks within the buffer keep it
+        match i64::try_from(pos as i128 - self.pos as i128) {
+            Ok(offset) => self.reader.seek_relative(offset)?,
+            Err(_) => {
+                self.reader.seek(SeekFrom::Start(pos))?;
+            }
+        }
+        self.pos = pos;
+        Ok(())
+    }
+
+    fn unread_byte(&mut self) -> Result<(), MidiError> {
+        if self.pos > 0 {
+            self.reader.seek_relative(-1)?;
+            self.pos -= 1;
+        }
+        Ok(())
+    }
+
+    fn total_len(&mut self) -> Result<u64, MidiError> {
+        if let Some(len) = self.len {
+            return Ok(len);
+        }
+        let len = self.reader.seek(SeekFrom::End(0))?;
+        self.reader.seek(SeekFrom::Start(self.pos))?;
+        self.len = Some(len);
+        Ok(len)
+    }
+}
+
+/// In-memory input that tracks its position with a plain index
+pub(crate) struct SliceReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        SliceReader { data, pos: 0 }
+    }
+
+    /// Error for a read past the end of the data, matching what a `Read` source reports
+    #[cfg(feature = "std")]
+    fn unexpected_eof(&self) -> MidiError {
+        MidiError::Io(io::Error::from(io::ErrorKind::UnexpectedEof))
+    }
+
+    /// Error for a read past the end of the data
+    #[cfg(not(feature = "std"))]
+    fn unexpected_eof(&self) -> MidiError {
+        MidiError::Format {
+            message: UNEXPECTED_EOF.into(),
+            offset: self.pos as u64,
+        }
+    }
+}
+
+impl MidiSource for SliceReader<'_> {
+    fn read_u8(&mut self) -> Result<u8, MidiError> {
+        let byte = *self
+            .data
+            .get(self.pos)
+            .ok_or_else(|| self.unexpected_eof())?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), MidiError> {
+        let end = self
+            .pos
+            .checked_add(buf.len())
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| self.unexpected_eof())?;
+        buf.copy_from_slice(&self.data[self.pos..end]);
+        self.pos = end;
+        Ok(())
+    }
+
+    fn position(&mut self) -> Result<u64, MidiError> {
+        Ok(self.pos as u64)
+    }
+
+    fn seek_to(&mut self, pos: u64) -> Result<(), MidiError> {
+        // Seeking past the end is allowed; the next read reports EOF
+        self.pos = usize::try_from(pos).unwrap_or(usize::MAX);
+        Ok(())
+    }
+
+    fn unread_byte(&mut self) -> Result<(), MidiError> {
+        self.pos = self.pos.saturating_sub(1);
+        Ok(())
+    }
+
+    fn total_len(&mut self) -> Result<u64, MidiError> {
+        Ok(self.data.len() as u64)
+    }
+}
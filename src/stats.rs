@@ -0,0 +1,124 @@
+use crate::{Channel, MidiFile, MidiMessage, MidiTrack};
+use alloc::collections::BTreeSet;
+
+/// Summary counts and ranges describing the contents of a track or file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackStats {
+    pub note_ons: usize,
+    pub note_offs: usize, // Includes NoteOns with velocity 0
+    pub control_changes: usize,
+    pub program_changes: usize,
+    pub min_velocity: Option<u8>, // NoteOn velocities; None without notes
+    pub max_velocity: Option<u8>,
+    pub mean_velocity: Option<f64>,
+    pub min_pitch: Option<u8>,
+    pub max_pitch: Option<u8>,
+    pub channels: BTreeSet<Channel>, // Channels of all channel messages
+}
+
+/// How the channel messages of a parsed track chunk were encoded, to judge whether
+/// re-encoding with running status would shrink it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct RunningStatusStats {
+    pub running: usize,  // Channel messages that reused the previous status byte
+    pub explicit: usize, // Channel messages with their own status byte
+}
+
+impl RunningStatusStats {
+    /// Fraction of channel messages that used running status, or None without any
+    pub fn running_ratio(&self) -> Option<f64> {
+        let total = self.running + self.explicit;
+        (total > 0).then(|| self.running as f64 / total as f64)
+    }
+}
+
+impl TrackStats {
+    /// Fold another set of statistics into this one
+    fn merge(&mut self, other: &TrackStats) {
+        let total = self.note_ons + other.note_ons;
+        if total > 0 {
+            let sum = self.mean_velocity.unwrap_or(0.0) * self.note_ons as f64
+                + other.mean_velocity.unwrap_or(0.0) * other.note_ons as f64;
+            self.mean_velocity = Some(sum / total as f64);
+        }
+
+        self.note_ons = total;
+        self.note_offs += other.note_offs;
+        self.control_changes += other.control_changes;
+        self.program_changes += other.program_changes;
+        self.min_velocity = min_option(self.min_velocity, other.min_velocity);
+        self.max_velocity = self.max_velocity.max(other.max_velocity);
+        self.min_pitch = min_option(self.min_pitch, other.min_pitch);
+        self.max_pitch = self.max_pitch.max(other.max_pitch);
+        self.channels.extend(&other.channels);
+    }
+}
+
+/// Smaller of two optional values, ignoring a missing one
+fn min_option(a: Option<u8>, b: Option<u8>) -> Option<u8> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+impl MidiTrack {
+    /// Count the track's notes and controllers and measure its velocity and pitch range
+    pub fn stats(&self) -> TrackStats {
+        let mut stats = TrackStats::default();
+        let mut velocity_sum = 0u64;
+
+        for event in &self.events {
+            if let Some(channel) = event.message.channel() {
+                stats.channels.insert(channel);
+            }
+
+            match event.message {
+                MidiMessage::NoteOn { note, velocity, .. } if velocity > 0 => {
+                    stats.note_ons += 1;
+                    velocity_sum += velocity as u64;
+                    stats.min_velocity = min_option(stats.min_velocity, Some(velocity));
+                    stats.max_velocity = stats.max_velocity.max(Some(velocity));
+                    stats.min_pitch = min_option(stats.min_pitch, Some(note));
+                    stats.max_pitch = stats.max_pitch.max(Some(note));
+                }
+                MidiMessage::NoteOn { .. } | MidiMessage::NoteOff { .. } => stats.note_offs += 1,
+                MidiMessage::ControlChange { .. } => stats.control_changes += 1,
+                MidiMessage::ProgramChange { .. } => stats.program_changes += 1,
+                _ => {}
+            }
+        }
+
+        if stats.note_ons > 0 {
+            stats.mean_velocity = Some(velocity_sum as f64 / stats.note_ons as f64);
+        }
+        stats
+    }
+
+    /// Bitmask of the channels the track's channel messages use, with bit N set for
+    /// zero-based channel N
+    pub fn channels_used(&self) -> u16 {
+        self.events
+            .iter()
+            .filter_map(|event| event.message.channel())
+            .fold(0, |mask, channel| mask | 1 << channel.get())
+    }
+}
+
+impl MidiFile {
+    /// Statistics aggregated across every track in the file
+    pub fn stats(&self) -> TrackStats {
+        let mut stats = TrackStats::default();
+        for track in &self.tracks {
+            stats.merge(&track.stats());
+        }
+        stats
+    }
+
+    /// Bitmask of the channels used by any track, with bit N set for zero-based channel N
+    pub fn channels_used(&self) -> u16 {
+        self.tracks
+            .iter()
+            .fold(0, |mask, track| mask | track.channels_used())
+    }
+}
@@ -0,0 +1,371 @@
+use crate::source::MidiSource;
+use crate::{
+    MetaEvent, MidiError, MidiEvent, MidiFile, MidiMessage, ParseOptions, ParseWarning,
+    RunningStatusStats,
+};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{Read, Seek};
+
+/// Where a track parse has got to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Reading,
+    Finishing, // EndOfTrack seen; the reader still has to be moved to the chunk end
+    Done,
+}
+
+/// Progress through the events of one track chunk, shared by every track parser
+#[derive(Debug, Clone)]
+pub(crate) struct TrackState {
+    start_pos: u64,
+    declared_length: u64,
+    end_pos: u64,
+    input_len: u64, // Measured once, as a seekable reader pays for every lookup
+    overran: bool,  // An event crossed the declared end, so read on to EndOfTrack
+    running_status: Option<u8>,
+    status_stats: RunningStatusStats,
+    phase: Phase,
+    warnings: Vec<ParseWarning>,
+}
+
+impl TrackState {
+    /// State for a track whose data starts at `start_pos` and is declared to be
+    /// `declared_length` bytes long.
+    ///
+    /// A declared length running past the end of the input is cut short with a warning.
+    pub(crate) fn new<S: MidiSource>(
+        reader: &mut S,
+        start_pos: u64,
+        declared_length: u64,
+    ) -> Result<Self, MidiError> {
+        let mut warnings = Vec::new();
+        let input_len = reader.total_len()?;
+        let available = input_len.saturating_sub(start_pos);
+        if declared_length > available {
+            warnings.push(ParseWarning::TrackLengthPastEof {
+                offset: start_pos,
+                declared: declared_length,
+                available,
+            });
+        }
+
+        Ok(TrackState {
+            start_pos,
+            declared_length,
+            end_pos: start_pos + declared_length.min(available),
+            input_len,
+            overran: false,
+            running_status: None,
+            status_stats: RunningStatusStats::default(),
+            phase: Phase::Reading,
+            warnings,
+        })
+    }
+
+    /// True if the declared track length runs past the end of the input
+    pub(crate) fn past_eof(&self) -> bool {
+        self.start_pos + self.declared_length > self.end_pos
+    }
+
+    /// Running-status counts for the events read so far
+    pub(crate) fn running_status_stats(&self) -> RunningStatusStats {
+        self.status_stats
+    }
+
+    /// Take the warnings recorded so far
+    pub(crate) fn take_warnings(&mut self) -> Vec<ParseWarning> {
+        core::mem::take(&mut self.warnings)
+    }
+
+    /// Parse the next event, or return None once the track is exhausted and the reader
+    /// sits at the end of the chunk
+    pub(crate) fn next_event<S: MidiSource>(
+        &mut self,
+        reader: &mut S,
+        options: &ParseOptions,
+    ) -> Option<Result<MidiEvent, MidiError>> {
+        match self.phase {
+            Phase::Done => None,
+            Phase::Finishing => {
+                self.phase = Phase::Done;
+                self.seek_to_end(reader).err().map(Err)
+            }
+            Phase::Reading => {
+                match reader.position() {
+                    Ok(pos) if pos >= self.end_pos && !self.overran => {
+                        self.phase = Phase::Done;
+                        return self.seek_to_end(reader).err().map(Err);
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        self.phase = Phase::Done;
+                        return Some(Err(err));
+                    }
+                }
+
+                match MidiFile::parse_event(
+                    reader,
+                    &mut self.running_status,
+                    &mut self.status_stats,
+                    // A meta or SysEx length may run past the declared end as long as
+                    // it fits in the input: check_overrun then treats the declared
+                    // length as too short
+                    self.input_len,
+                    options,
+                ) {
+                    Ok(event) => {
+                        if let Err(err) = self.check_overrun(reader, &event) {
+                            self.phase = Phase::Done;
+                            return Some(Err(err));
+                        }
+                        // Stop at the end of track event even if the chunk has bytes left
+                        if event.message.is_end_of_track() {
+                            if let Err(err) = self.check_overlong(reader) {
+                                self.phase = Phase::Done;
+                                return Some(Err(err));
+                            }
+                            self.phase = Phase::Finishing;
+                        }
+                        Some(Ok(event))
+                    }
+                    Err(err) => {
+                        self.phase = Phase::Done;
+                        Some(Err(err))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Notice an event ending past the declared chunk end: the declared length is too
+    /// short, so keep reading to EndOfTrack and treat that as the real end of the chunk
+    fn check_overrun<S: MidiSource>(
+        &mut self,
+        reader: &mut S,
+        event: &MidiEvent,
+    ) -> Result<(), MidiError> {
+        let pos = reader.position()?;
+        if pos > self.end_pos {
+            self.overran = true;
+        }
+        if self.overran && matches!(event.message, MidiMessage::Meta(MetaEvent::EndOfTrack)) {
+            self.end_pos = pos;
+            self.warnings.push(ParseWarning::TrackLengthTooShort {
+                offset: self.start_pos,
+                declared: self.declared_length,
+                actual: pos - self.start_pos,
+            });
+        }
+        Ok(())
+    }
+
+    /// At an EndOfTrack before the declared chunk end, check what follows the declared
+    /// end: if it is not a chunk header but the bytes after the EndOfTrack are, the
+    /// declared length is too long, so end the chunk at the EndOfTrack instead
+    fn check_overlong<S: MidiSource>(&mut self, reader: &mut S) -> Result<(), MidiError> {
+        let pos = reader.position()?;
+        if self.overran || pos >= self.end_pos || self.end_pos >= self.input_len {
+            return Ok(());
+        }
+        let chunk_id_at = |reader: &mut S, at: u64| -> Result<bool, MidiError> {
+            if at == self.input_len {
+                return Ok(true);
+            }
+            if at + 4 > self.input_len {
+                return Ok(false);
+            }
+            let mut id = [0u8; 4];
+            reader.seek_to(at)?;
+            reader.read_exact(&mut id)?;
+            Ok(MidiFile::is_chunk_id(&id))
+        };
+        let resync = !chunk_id_at(reader, self.end_pos)? && chunk_id_at(reader, pos)?;
+        reader.seek_to(pos)?;
+        if resync {
+            self.end_pos = pos;
+            self.warnings.push(ParseWarning::TrackLengthTooLong {
+                offset: self.start_pos,
+                declared: self.declared_length,
+                actual: pos - self.start_pos,
+            });
+        }
+        Ok(())
+    }
+
+    /// Make sure we're at the correct position after the track
+    fn seek_to_end<S: MidiSource>(&self, reader: &mut S) -> Result<(), MidiError> {
+        if reader.position()? != self.end_pos {
+            reader.seek_to(self.end_pos)?;
+        }
+        Ok(())
+    }
+}
+
+/// Streaming parser yielding the events of one MTrk chunk as they are read
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct TrackParser<R> {
+    reader: R,
+    state: TrackState,
+    options: ParseOptions,
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> TrackParser<R> {
+    /// Start parsing the track chunk at the reader's current position
+    pub fn new(reader: R) -> Result<Self, MidiError> {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    /// Start parsing the track chunk at the reader's current position using the given options
+    pub fn with_options(mut reader: R, options: ParseOptions) -> Result<Self, MidiError> {
+        let (track_start_pos, track_length) = MidiFile::parse_track_header(&mut reader)?;
+        let state = TrackState::new(&mut reader, track_start_pos, track_length)?;
+        Ok(TrackParser {
+            reader,
+            state,
+            options,
+        })
+    }
+
+    /// Irregularities in the chunk that were worked around so far
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.state.warnings
+    }
+
+    /// How many of the channel messages read so far used running status
+    pub fn running_status_stats(&self) -> RunningStatusStats {
+        self.state.running_status_stats()
+    }
+
+    /// Give back the reader, positioned after the track once iteration has finished
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read + Seek> Iterator for TrackParser<R> {
+    type Item = Result<MidiEvent, MidiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.state.next_event(&mut self.reader, &self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Channel;
+    use alloc::vec;
+
+    const NOTE_TRACK: [u8; 12] = [
+        0x00, 0x90, 0x3C, 0x40, 0x10, 0x80, 0x3C, 0x40, 0x00, 0xFF, 0x2F, 0x00,
+    ];
+    const EMPTY_TRACK: [u8; 4] = [0x00, 0xFF, 0x2F, 0x00];
+
+    /// A format-1 file whose tracks are (declared length, events)
+    fn file_with_lengths(tracks: &[(u32, &[u8])]) -> Vec<u8> {
+        let mut data = b"MThd\0\0\0\x06\0\x01".to_vec();
+        data.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+        data.extend_from_slice(&96u16.to_be_bytes());
+        for (length, events) in tracks {
+            data.extend_from_slice(b"MTrk");
+            data.extend_from_slice(&length.to_be_bytes());
+            data.extend_from_slice(events);
+        }
+        data
+    }
+
+    fn note_messages() -> Vec<MidiMessage> {
+        let channel = Channel::new(0).unwrap();
+        vec![
+            MidiMessage::NoteOn {
+                channel,
+                note: 0x3C,
+                velocity: 0x40,
+            },
+            MidiMessage::NoteOff {
+                channel,
+                note: 0x3C,
+                velocity: 0x40,
+            },
+            MidiMessage::Meta(MetaEvent::EndOfTrack),
+        ]
+    }
+
+    fn track_messages(file: &MidiFile, index: usize) -> Vec<MidiMessage> {
+        file.tracks[index]
+            .events
+            .iter()
+            .map(|e| e.message.clone())
+            .collect()
+    }
+
+    #[test]
+    fn short_length_reads_on_to_end_of_track() {
+        let data = file_with_lengths(&[(5, &NOTE_TRACK), (4, &EMPTY_TRACK)]);
+        let parse = MidiFile::from_bytes_lenient(&data).unwrap();
+        assert_eq!(track_messages(&parse.file, 0), note_messages());
+        assert_eq!(parse.file.tracks.len(), 2);
+        assert_eq!(
+            parse.warnings,
+            [ParseWarning::TrackLengthTooShort {
+                offset: 22,
+                declared: 5,
+                actual: 12,
+            }]
+        );
+        assert!(MidiFile::from_bytes(&data).is_ok());
+    }
+
+    #[test]
+    fn long_length_stops_at_end_of_track() {
+        let data = file_with_lengths(&[(20, &NOTE_TRACK), (4, &EMPTY_TRACK)]);
+        let parse = MidiFile::from_bytes_lenient(&data).unwrap();
+        assert_eq!(track_messages(&parse.file, 0), note_messages());
+        assert_eq!(
+            track_messages(&parse.file, 1),
+            [MidiMessage::Meta(MetaEvent::EndOfTrack)]
+        );
+        assert_eq!(
+            parse.warnings,
+            [ParseWarning::TrackLengthTooLong {
+                offset: 22,
+                declared: 20,
+                actual: 12,
+            }]
+        );
+    }
+
+    #[test]
+    fn length_past_eof_reads_to_the_end() {
+        let data = file_with_lengths(&[(1000, &NOTE_TRACK)]);
+        let parse = MidiFile::from_bytes_lenient(&data).unwrap();
+        assert_eq!(track_messages(&parse.file, 0), note_messages());
+        assert_eq!(
+            parse.warnings,
+            [ParseWarning::TrackLengthPastEof {
+                offset: 22,
+                declared: 1000,
+                available: 12,
+            }]
+        );
+        assert!(MidiFile::from_bytes(&data).is_ok());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn track_parser_reports_the_wrong_length() {
+        let data = file_with_lengths(&[(5, &NOTE_TRACK)]);
+        let mut reader = std::io::Cursor::new(&data[14..]);
+        let mut parser = TrackParser::new(&mut reader).unwrap();
+        let messages: Vec<MidiMessage> = parser.by_ref().map(|e| e.unwrap().message).collect();
+        assert_eq!(messages, note_messages());
+        assert!(matches!(
+            parser.warnings(),
+            [ParseWarning::TrackLengthTooShort { .. }]
+        ));
+    }
+}
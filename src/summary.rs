@@ -0,0 +1,83 @@
+use crate::{Channel, MidiError, MidiFile};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Extensions, compared without case, of the files `summarize_dir` parses
+const MIDI_EXTENSIONS: [&str; 3] = ["mid", "midi", "rmi"];
+
+/// Overview of one MIDI file for a catalog or library index
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSummary {
+    pub format: u16,
+    pub num_tracks: usize, // Track chunks actually read
+    pub duration_seconds: f64,
+    pub instruments: Vec<(Channel, u8, &'static str)>, // As from `instrument_names`
+}
+
+impl MidiFile {
+    /// Format, track count, length and instruments of the file
+    pub fn summary(&self) -> FileSummary {
+        FileSummary {
+            format: self.header.format,
+            num_tracks: self.tracks.len(),
+            duration_seconds: self.duration_seconds(),
+            instruments: self.instrument_names(),
+        }
+    }
+
+    /// Summarize every MIDI file (`.mid`, `.midi` or `.rmi`) under `dir`, including its
+    /// subdirectories, sorted by path.
+    ///
+    /// Symlinks to directories are not followed, so a link loop cannot repeat files,
+    /// while symlinks to MIDI files are summarized like the files themselves.
+    ///
+    /// A file that fails to parse gets its error in place of a summary, as does a
+    /// directory that cannot be listed, so one bad file never stops the batch.
+    pub fn summarize_dir<P: AsRef<Path>>(dir: P) -> Vec<(PathBuf, Result<FileSummary, MidiError>)> {
+        let mut results = Vec::new();
+        collect_summaries(dir.as_ref(), &mut results);
+        results.sort_by(|(a, _), (b, _)| a.cmp(b));
+        results
+    }
+}
+
+/// Summarize the MIDI files in `dir` and its subdirectories into `results`
+fn collect_summaries(dir: &Path, results: &mut Vec<(PathBuf, Result<FileSummary, MidiError>)>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            results.push((dir.to_path_buf(), Err(err.into())));
+            return;
+        }
+    };
+
+    for entry in entries {
+        let entry = entry.and_then(|entry| Ok((entry.path(), entry.file_type()?)));
+        let (path, file_type) = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                results.push((dir.to_path_buf(), Err(err.into())));
+                continue;
+            }
+        };
+
+        // The entry's own file type does not follow symlinks, unlike Path::is_dir
+        if file_type.is_dir() {
+            collect_summaries(&path, results);
+        } else if is_midi_path(&path) {
+            let summary = MidiFile::open(&path).map(|file| file.summary());
+            results.push((path, summary));
+        }
+    }
+}
+
+/// True if the path has one of the `MIDI_EXTENSIONS`
+fn is_midi_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            MIDI_EXTENSIONS
+                .iter()
+                .any(|known| extension.eq_ignore_ascii_case(known))
+        })
+}
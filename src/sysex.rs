@@ -0,0 +1,115 @@
+/// Manufacturer ID at the start of a SysEx message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ManufacturerId {
+    Standard(u8),         // One-byte vendor ID, 0x01-0x7C
+    Extended(u8, u8),     // Three-byte vendor ID 0x00 xx xx, without the leading zero
+    NonCommercial,        // 0x7D, reserved for research and education
+    UniversalNonRealTime, // 0x7E
+    UniversalRealTime,    // 0x7F
+}
+
+/// Names of common one-byte manufacturer IDs
+const STANDARD_NAMES: [(u8, &str); 14] = [
+    (0x01, "Sequential Circuits"),
+    (0x04, "Moog"),
+    (0x06, "Lexicon"),
+    (0x07, "Kurzweil"),
+    (0x0F, "Ensoniq"),
+    (0x10, "Oberheim"),
+    (0x11, "Apple"),
+    (0x18, "E-mu"),
+    (0x40, "Kawai"),
+    (0x41, "Roland"),
+    (0x42, "Korg"),
+    (0x43, "Yamaha"),
+    (0x44, "Casio"),
+    (0x47, "Akai"),
+];
+
+/// Names of common three-byte manufacturer IDs
+const EXTENDED_NAMES: [((u8, u8), &str); 5] = [
+    ((0x20, 0x29), "Novation"),
+    ((0x20, 0x32), "Behringer"),
+    ((0x20, 0x3C), "Elektron"),
+    ((0x20, 0x6B), "Arturia"),
+    ((0x21, 0x09), "Native Instruments"),
+];
+
+impl ManufacturerId {
+    /// Vendor name for a well-known ID, or None if it is not in the table
+    pub fn name(&self) -> Option<&'static str> {
+        match *self {
+            ManufacturerId::Standard(id) => STANDARD_NAMES
+                .iter()
+                .find(|(known, _)| *known == id)
+                .map(|(_, name)| *name),
+            ManufacturerId::Extended(high, low) => EXTENDED_NAMES
+                .iter()
+                .find(|(known, _)| *known == (high, low))
+                .map(|(_, name)| *name),
+            ManufacturerId::NonCommercial => Some("Non-Commercial"),
+            ManufacturerId::UniversalNonRealTime => Some("Universal Non-Real Time"),
+            ManufacturerId::UniversalRealTime => Some("Universal Real Time"),
+        }
+    }
+}
+
+/// Decode the manufacturer ID at the start of SysEx data, as stored in a
+/// `MidiMessage::SysEx` (a leading 0xF0 status byte is skipped if present).
+///
+/// Returns None if the data is too short or does not start with an ID.
+pub fn sysex_manufacturer(data: &[u8]) -> Option<ManufacturerId> {
+    let data = data.strip_prefix(&[0xF0]).unwrap_or(data);
+    match *data {
+        [0x00, high, low, ..] if high < 0x80 && low < 0x80 => {
+            Some(ManufacturerId::Extended(high, low))
+        }
+        [0x7D, ..] => Some(ManufacturerId::NonCommercial),
+        [0x7E, ..] => Some(ManufacturerId::UniversalNonRealTime),
+        [0x7F, ..] => Some(ManufacturerId::UniversalRealTime),
+        [id, ..] if (0x01..0x7D).contains(&id) => Some(ManufacturerId::Standard(id)),
+        _ => None,
+    }
+}
+
+/// A well-known SysEx message that switches a sound module into a standard mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SysExKind {
+    GmSystemOn,  // F0 7E <device> 09 01 F7
+    GmSystemOff, // F0 7E <device> 09 02 F7
+    Gm2SystemOn, // F0 7E <device> 09 03 F7
+    GsReset,     // F0 41 <device> 42 12 40 00 7F 00 41 F7
+    XgSystemOn,  // F0 43 1<n> 4C 00 00 7E 00 F7
+}
+
+impl SysExKind {
+    /// Display name, e.g. "GM System On"
+    pub fn name(&self) -> &'static str {
+        match self {
+            SysExKind::GmSystemOn => "GM System On",
+            SysExKind::GmSystemOff => "GM System Off",
+            SysExKind::Gm2SystemOn => "GM2 System On",
+            SysExKind::GsReset => "GS Reset",
+            SysExKind::XgSystemOn => "XG System On",
+        }
+    }
+}
+
+/// Recognize the GM, GM2, GS and XG reset messages in SysEx data, as stored in a
+/// `MidiMessage::SysEx` (a leading 0xF0 and the closing 0xF7 are both optional).
+///
+/// Any device ID is accepted. Returns None for every other message.
+pub fn classify_sysex(data: &[u8]) -> Option<SysExKind> {
+    let data = data.strip_prefix(&[0xF0]).unwrap_or(data);
+    let data = data.strip_suffix(&[0xF7]).unwrap_or(data);
+    match *data {
+        [0x7E, _, 0x09, 0x01] => Some(SysExKind::GmSystemOn),
+        [0x7E, _, 0x09, 0x02] => Some(SysExKind::GmSystemOff),
+        [0x7E, _, 0x09, 0x03] => Some(SysExKind::Gm2SystemOn),
+        [0x41, _, 0x42, 0x12, 0x40, 0x00, 0x7F, 0x00, 0x41] => Some(SysExKind::GsReset),
+        [0x43, device, 0x4C, 0x00, 0x00, 0x7E, 0x00] if device & 0xF0 == 0x10 => {
+            Some(SysExKind::XgSystemOn)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{MetaEvent, MidiFile, MidiMessage, MidiTrack, ParseOptions};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+/// Character encoding of the text in meta events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TextEncoding {
+    /// UTF-8, replacing invalid sequences with U+FFFD
+    #[default]
+    Utf8Lossy,
+    /// ISO-8859-1, where every byte is the code point of the same value
+    Latin1,
+    /// Shift JIS, common in Japanese files
+    #[cfg(feature = "shift_jis")]
+    ShiftJis,
+}
+
+impl TextEncoding {
+    /// Decode text bytes into a string
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            TextEncoding::Utf8Lossy => String::from_utf8_lossy(bytes).into_owned(),
+            TextEncoding::Latin1 => bytes.iter().map(|&byte| byte as char).collect(),
+            #[cfg(feature = "shift_jis")]
+            TextEncoding::ShiftJis => encoding_rs::SHIFT_JIS
+                .decode_without_bom_handling(bytes)
+                .0
+                .into_owned(),
+        }
+    }
+}
+
+/// Text of a text meta event (types 0x01-0x07), with the bytes it was decoded from when
+/// they were kept
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetaText {
+    text: String,
+    raw: Option<Vec<u8>>, // Original bytes, written back instead of the text
+}
+
+impl MetaText {
+    /// Text that is written to files as UTF-8
+    pub fn new(text: impl Into<String>) -> Self {
+        MetaText {
+            text: text.into(),
+            raw: None,
+        }
+    }
+
+    /// Decode `raw` with `encoding`, keeping the bytes so they are written back exactly
+    pub fn from_raw(raw: Vec<u8>, encoding: TextEncoding) -> Self {
+        MetaText {
+            text: encoding.decode(&raw),
+            raw: Some(raw),
+        }
+    }
+
+    /// Decode the data of a parsed text event as `options` ask
+    pub(crate) fn parsed(data: Vec<u8>, options: &ParseOptions) -> Self {
+        if options.keep_raw_text {
+            MetaText::from_raw(data, options.text_encoding)
+        } else {
+            MetaText::new(options.text_encoding.decode(&data))
+        }
+    }
+
+    /// The decoded text
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The original bytes, if they were kept
+    pub fn raw(&self) -> Option<&[u8]> {
+        self.raw.as_deref()
+    }
+
+    /// Bytes written to a file: the original ones if kept, otherwise the text as UTF-8
+    pub fn as_bytes(&self) -> &[u8] {
+        self.raw.as_deref().unwrap_or(self.text.as_bytes())
+    }
+}
+
+/// Hashes only the bytes written to a file, so whether the raw bytes were kept does not
+/// change the hash
+impl Hash for MetaText {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state);
+    }
+}
+
+impl From<&str> for MetaText {
+    fn from(text: &str) -> Self {
+        MetaText::new(text)
+    }
+}
+
+impl From<String> for MetaText {
+    fn from(text: String) -> Self {
+        MetaText::new(text)
+    }
+}
+
+impl fmt::Display for MetaText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl MetaEvent {
+    /// Decoded text of a text meta event (types 0x01-0x07), replacing whatever could not
+    /// be decoded; None for other events
+    pub fn text_lossy(&self) -> Option<&str> {
+        self.meta_text().map(MetaText::as_str)
+    }
+
+    /// Bytes a text meta event is written with; None for other events
+    pub fn text_bytes(&self) -> Option<&[u8]> {
+        self.meta_text().map(MetaText::as_bytes)
+    }
+
+    /// The MetaText of a text meta event
+    fn meta_text(&self) -> Option<&MetaText> {
+        match self {
+            MetaEvent::Text(text)
+            | MetaEvent::CopyrightNotice(text)
+            | MetaEvent::TrackName(text)
+            | MetaEvent::InstrumentName(text)
+            | MetaEvent::Lyrics(text)
+            | MetaEvent::Marker(text)
+            | MetaEvent::CuePoint(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+impl MidiFile {
+    /// Title of the song: the name of the first track, which by convention holds the
+    /// sequence name in format 0 and 1 files. None if that track has no TrackName.
+    pub fn name(&self) -> Option<&str> {
+        self.tracks.first()?.name()
+    }
+}
+
+impl MidiTrack {
+    /// Text of the track's first TrackName meta event, if it has one
+    pub fn name(&self) -> Option<&str> {
+        self.events.iter().find_map(|event| match &event.message {
+            MidiMessage::Meta(MetaEvent::TrackName(name)) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
@@ -0,0 +1,623 @@
+use crate::{MetaEvent, MidiError, MidiEvent, MidiFile, MidiMessage, MidiTrack, TimeDivision};
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Tempo assumed before the first SetTempo event (120 BPM)
+pub const DEFAULT_TEMPO: u32 = 500_000;
+
+/// Ticks per quarter note substituted for a zero time division when parsing leniently
+pub const DEFAULT_TIME_DIVISION: u16 = 480;
+
+/// Largest tempo a SetTempo event can hold (24 bits of microseconds per quarter note)
+const MAX_TEMPO: u32 = 0xFF_FFFF;
+
+/// Beats per minute for a tempo in microseconds per quarter note (500000 is 120 BPM)
+pub fn bpm_from_tempo(us_per_quarter: u32) -> f64 {
+    60_000_000.0 / us_per_quarter as f64
+}
+
+/// Tempo in microseconds per quarter note for a BPM, rounded and clamped to what a
+/// SetTempo event can hold. A BPM that is not positive gives the slowest tempo.
+pub fn tempo_from_bpm(bpm: f64) -> u32 {
+    let tempo = 60_000_000.0 / bpm;
+    if tempo.is_nan() || tempo < 0.0 || tempo >= MAX_TEMPO as f64 {
+        return MAX_TEMPO;
+    }
+    (round(tempo) as u32).max(1)
+}
+
+/// Time signature assumed before the first TimeSignature event, as (numerator, denominator)
+pub const DEFAULT_TIME_SIGNATURE: (u8, u8) = (4, 4);
+
+impl MidiTrack {
+    /// Absolute tick position of every event, aligned with `events`
+    pub fn absolute_times(&self) -> Vec<u64> {
+        self.iter_absolute().map(|(tick, _)| tick).collect()
+    }
+
+    /// Iterate over events paired with their absolute tick position
+    pub fn iter_absolute(&self) -> impl Iterator<Item = (u64, &MidiEvent)> + '_ {
+        self.events.iter().scan(0u64, |tick, event| {
+            *tick += event.delta_time as u64;
+            Some((*tick, event))
+        })
+    }
+
+    /// True if the last event is an EndOfTrack
+    pub(crate) fn ends_with_end_of_track(&self) -> bool {
+        self.events
+            .last()
+            .is_some_and(|event| event.message.is_end_of_track())
+    }
+
+    /// Build a track from messages at absolute ticks, given in non-decreasing tick order
+    pub(crate) fn from_absolute<I>(events: I) -> Self
+    where
+        I: IntoIterator<Item = (u64, MidiMessage)>,
+    {
+        let mut previous_tick = 0;
+        let events = events
+            .into_iter()
+            .map(|(tick, message)| {
+                let delta = tick.saturating_sub(previous_tick);
+                previous_tick = tick;
+                MidiEvent {
+                    delta_time: u32::try_from(delta).unwrap_or(u32::MAX),
+                    message,
+                }
+            })
+            .collect();
+        MidiTrack { events }
+    }
+}
+
+impl MidiFile {
+    /// Ticks per quarter note, or None if the file is timed in SMPTE frames
+    pub fn ticks_per_quarter(&self) -> Option<u16> {
+        match self.header.time_division_parsed() {
+            TimeDivision::TicksPerQuarter(tpq) => Some(tpq),
+            TimeDivision::Smpte { .. } => None,
+        }
+    }
+
+    /// SMPTE timing as (frames per second, ticks per frame), or None if the file is timed
+    /// in ticks per quarter note. A rate of 29 means 29.97 drop-frame.
+    pub fn smpte_timing(&self) -> Option<(u8, u8)> {
+        match self.header.time_division_parsed() {
+            TimeDivision::TicksPerQuarter(_) => None,
+            TimeDivision::Smpte {
+                fps,
+                ticks_per_frame,
+            } => Some((fps, ticks_per_frame)),
+        }
+    }
+
+    /// Build a tempo map from the file's SetTempo events
+    pub fn tempo_map(&self) -> TempoMap {
+        TempoMap::new(self)
+    }
+
+    /// Build a time signature map from the file's TimeSignature events
+    pub fn time_signature_map(&self) -> TimeSignatureMap {
+        TimeSignatureMap::new(self)
+    }
+
+    /// Musical position of a tick as 1-based (bar, beat, tick within the beat), following
+    /// the file's time signature changes.
+    ///
+    /// A signature change that falls mid-bar starts a new bar. SMPTE-timed files have no
+    /// quarter-note grid, so beats are measured at the default tempo of 120 BPM.
+    pub fn musical_position(&self, tick: u64) -> (u32, u32, u32) {
+        let ticks_per_quarter = match self.header.time_division_parsed() {
+            TimeDivision::TicksPerQuarter(tpq) => tpq as u64,
+            TimeDivision::Smpte {
+                fps,
+                ticks_per_frame,
+            } => {
+                let fps = if fps == 29 { 30 } else { fps as u64 };
+                fps * ticks_per_frame as u64 * DEFAULT_TEMPO as u64 / 1_000_000
+            }
+        };
+        self.time_signature_map()
+            .musical_position(tick, ticks_per_quarter)
+    }
+
+    /// Length of the file in ticks: the latest end tick across all tracks
+    pub fn duration_ticks(&self) -> u64 {
+        self.tracks
+            .iter()
+            .map(|track| track.events.iter().map(|e| e.delta_time as u64).sum())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Length of the file in seconds, following its tempo changes
+    pub fn duration_seconds(&self) -> f64 {
+        self.tempo_map().ticks_to_seconds(self.duration_ticks())
+    }
+
+    /// Change the playback speed by `factor`, which multiplies the BPM of every SetTempo
+    /// event: 2.0 plays twice as fast and halves the duration, 0.5 plays at half speed
+    /// and doubles it. The resulting microseconds per quarter note are clamped to
+    /// 1..=0xFFFFFF, what SetTempo can hold.
+    ///
+    /// A file without SetTempo events gets one at tick 0 of its first track, scaled from
+    /// the default 120 BPM. Fails, changing nothing, if `factor` is not finite and
+    /// positive.
+    pub fn scale_tempo(&mut self, factor: f64) -> Result<(), MidiError> {
+        if !factor.is_finite() || factor <= 0.0 {
+            return Err(MidiError::InvalidOperation(format!(
+                "Tempo scale factor must be finite and positive, got {}",
+                factor
+            )));
+        }
+        let scale = |tempo: u32| tempo_from_bpm(bpm_from_tempo(tempo) * factor);
+
+        let mut found = false;
+        for event in self.tracks.iter_mut().flat_map(|track| &mut track.events) {
+            if let MidiMessage::Meta(MetaEvent::SetTempo(tempo)) = &mut event.message {
+                *tempo = scale(*tempo);
+                found = true;
+            }
+        }
+
+        if !found {
+            if let Some(track) = self.tracks.first_mut() {
+                track.events.insert(
+                    0,
+                    MidiEvent {
+                        delta_time: 0,
+                        message: MidiMessage::Meta(MetaEvent::SetTempo(scale(DEFAULT_TEMPO))),
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Absolute tick of the earliest NoteOn in any track, or None if nothing is played
+    pub fn first_note_tick(&self) -> Option<u64> {
+        self.tracks
+            .iter()
+            .filter_map(|track| {
+                track
+                    .iter_absolute()
+                    .find(|(_, event)| event.message.is_note_on())
+                    .map(|(tick, _)| tick)
+            })
+            .min()
+    }
+
+    /// Seconds from the start of the file to the first NoteOn, following tempo changes
+    pub fn leading_silence_seconds(&self) -> Option<f64> {
+        let tick = self.first_note_tick()?;
+        Some(self.tempo_map().ticks_to_seconds(tick))
+    }
+
+    /// Iterate over the events of all tracks as one timeline of (tick, track index, event),
+    /// ordered by absolute tick with ties broken by track index
+    pub fn merged_events(&self) -> impl Iterator<Item = (u64, usize, &MidiEvent)> + '_ {
+        let mut merged: Vec<(u64, usize, &MidiEvent)> = self
+            .tracks
+            .iter()
+            .enumerate()
+            .flat_map(|(index, track)| {
+                track
+                    .iter_absolute()
+                    .map(move |(tick, event)| (tick, index, event))
+            })
+            .collect();
+        // Stable sort keeps the original order of events within each track
+        merged.sort_by_key(|&(tick, index, _)| (tick, index));
+        merged.into_iter()
+    }
+}
+
+/// A stretch of the timeline played at a single tempo
+#[derive(Debug, Clone)]
+struct TempoSegment {
+    start_tick: u64,
+    start_seconds: f64,
+    tempo: u32, // Microseconds per quarter note
+}
+
+/// Maps tick positions to wall-clock time using a file's tempo changes
+#[derive(Debug, Clone)]
+pub struct TempoMap {
+    time_division: TimeDivision,
+    segments: Vec<TempoSegment>,
+}
+
+impl TempoMap {
+    /// Collect every SetTempo event in the file into a tempo map
+    pub fn new(file: &MidiFile) -> Self {
+        let mut changes: Vec<(u64, u32)> = file
+            .tracks
+            .iter()
+            .flat_map(|track| track.iter_absolute())
+            .filter_map(|(tick, event)| match event.message {
+                MidiMessage::Meta(MetaEvent::SetTempo(tempo)) => Some((tick, tempo)),
+                _ => None,
+            })
+            .collect();
+        // Stable sort keeps track order for changes on the same tick
+        changes.sort_by_key(|&(tick, _)| tick);
+
+        let time_division = file.header.time_division_parsed();
+        let mut segments = vec![TempoSegment {
+            start_tick: 0,
+            start_seconds: 0.0,
+            tempo: DEFAULT_TEMPO,
+        }];
+        for (tick, tempo) in changes {
+            let last = segments.last().unwrap();
+            let start_seconds = last.start_seconds
+                + Self::segment_seconds(time_division, last.tempo, tick - last.start_tick);
+            segments.push(TempoSegment {
+                start_tick: tick,
+                start_seconds,
+                tempo,
+            });
+        }
+
+        TempoMap {
+            time_division,
+            segments,
+        }
+    }
+
+    /// Convert an absolute tick position to seconds from the start of the file
+    pub fn ticks_to_seconds(&self, tick: u64) -> f64 {
+        let segment = segment_at(&self.segments, tick, |s| s.start_tick);
+        segment.start_seconds
+            + Self::segment_seconds(self.time_division, segment.tempo, tick - segment.start_tick)
+    }
+
+    /// Tempo in beats per minute at the given tick
+    pub fn bpm_at(&self, tick: u64) -> f64 {
+        bpm_from_tempo(segment_at(&self.segments, tick, |s| s.start_tick).tempo)
+    }
+
+    /// Duration in seconds of `ticks` ticks played at `tempo`
+    fn segment_seconds(time_division: TimeDivision, tempo: u32, ticks: u64) -> f64 {
+        match time_division {
+            TimeDivision::TicksPerQuarter(tpq) => {
+                ticks as f64 * tempo as f64 / 1_000_000.0 / tpq as f64
+            }
+            // SMPTE timing is absolute, so tempo changes have no effect
+            TimeDivision::Smpte {
+                fps,
+                ticks_per_frame,
+            } => {
+                let frames_per_second = match fps {
+                    29 => 30_000.0 / 1001.0, // 29.97 drop-frame
+                    fps => fps as f64,
+                };
+                ticks as f64 / (frames_per_second * ticks_per_frame as f64)
+            }
+        }
+    }
+}
+
+/// A stretch of the timeline in a single time signature
+#[derive(Debug, Clone)]
+struct TimeSignatureSegment {
+    start_tick: u64,
+    numerator: u8,
+    denominator: u8,
+}
+
+impl TimeSignatureSegment {
+    /// Length in ticks of one beat (the denominator's note value) and one bar
+    fn beat_and_bar_ticks(&self, ticks_per_quarter: u64) -> (u64, u64) {
+        let beat_ticks = (ticks_per_quarter * 4 / self.denominator.max(1) as u64).max(1);
+        (beat_ticks, beat_ticks * self.numerator.max(1) as u64)
+    }
+}
+
+/// Clamp a count to u32
+fn saturate(value: u64) -> u32 {
+    u32::try_from(value).unwrap_or(u32::MAX)
+}
+
+/// Round half away from zero, by hand as `f64::round` needs std
+pub(crate) fn round(value: f64) -> f64 {
+    if value < 0.0 {
+        -round(-value)
+    } else {
+        (value + 0.5) as u64 as f64
+    }
+}
+
+/// Take a trailing EndOfTrack off messages at absolute ticks, so events added at the
+/// end can be put back in front of it
+pub(crate) fn pop_end_of_track(events: &mut Vec<(u64, MidiMessage)>) -> Option<(u64, MidiMessage)> {
+    events.pop_if(|(_, message)| message.is_end_of_track())
+}
+
+/// The segment in force at `tick`, from segments sorted by the start tick given by
+/// `start_tick`
+fn segment_at<T>(segments: &[T], tick: u64, start_tick: impl Fn(&T) -> u64) -> &T {
+    // The first segment starts at tick 0, so the index is never zero
+    let index = segments.partition_point(|segment| start_tick(segment) <= tick);
+    &segments[index - 1]
+}
+
+/// Maps tick positions to the time signature in force, using a file's TimeSignature events
+#[derive(Debug, Clone)]
+pub struct TimeSignatureMap {
+    segments: Vec<TimeSignatureSegment>,
+}
+
+impl TimeSignatureMap {
+    /// Collect every TimeSignature event in the file into a time signature map
+    pub fn new(file: &MidiFile) -> Self {
+        let mut changes: Vec<(u64, u8, u8)> = file
+            .tracks
+            .iter()
+            .flat_map(|track| track.iter_absolute())
+            .filter_map(|(tick, event)| match event.message {
+                MidiMessage::Meta(MetaEvent::TimeSignature {
+                    numerator,
+                    denominator,
+                    ..
+                }) => Some((tick, numerator, denominator)),
+                _ => None,
+            })
+            .collect();
+        // Stable sort keeps track order for changes on the same tick
+        changes.sort_by_key(|&(tick, _, _)| tick);
+
+        let (numerator, denominator) = DEFAULT_TIME_SIGNATURE;
+        let mut segments = vec![TimeSignatureSegment {
+            start_tick: 0,
+            numerator,
+            denominator,
+        }];
+        segments.extend(
+            changes.into_iter().map(
+                |(start_tick, numerator, denominator)| TimeSignatureSegment {
+                    start_tick,
+                    numerator,
+                    denominator,
+                },
+            ),
+        );
+
+        TimeSignatureMap { segments }
+    }
+
+    /// Time signature in force at the given tick, as (numerator, denominator)
+    pub fn signature_at(&self, tick: u64) -> (u8, u8) {
+        let segment = segment_at(&self.segments, tick, |s| s.start_tick);
+        (segment.numerator, segment.denominator)
+    }
+
+    /// Position of a tick as 1-based (bar, beat, tick within the beat)
+    fn musical_position(&self, tick: u64, ticks_per_quarter: u64) -> (u32, u32, u32) {
+        let mut bar = 0u64;
+        for (index, segment) in self.segments.iter().enumerate() {
+            let (beat_ticks, bar_ticks) = segment.beat_and_bar_ticks(ticks_per_quarter);
+            let end_tick = self.segments.get(index + 1).map(|next| next.start_tick);
+            match end_tick {
+                // Whole segment before the tick: count its bars, a partial last bar included
+                Some(end_tick) if end_tick <= tick => {
+                    bar += (end_tick - segment.start_tick).div_ceil(bar_ticks);
+                }
+                _ => {
+                    let offset = tick - segment.start_tick;
+                    bar += offset / bar_ticks;
+                    let within_bar = offset % bar_ticks;
+                    return (
+                        saturate(bar + 1),
+                        saturate(within_bar / beat_ticks + 1),
+                        saturate(within_bar % beat_ticks),
+                    );
+                }
+            }
+        }
+        unreachable!("the last segment runs to the end of the timeline")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MidiHeader;
+
+    /// A file with the given time division and one track per list of messages at
+    /// absolute ticks
+    fn file_with_tracks(time_division: u16, tracks: Vec<Vec<(u64, MidiMessage)>>) -> MidiFile {
+        MidiFile {
+            header: MidiHeader {
+                format: 1,
+                num_tracks: tracks.len() as u16,
+                time_division,
+            },
+            tracks: tracks.into_iter().map(MidiTrack::from_absolute).collect(),
+            chunks: Vec::new(),
+        }
+    }
+
+    fn tempo(tick: u64, tempo: u32) -> (u64, MidiMessage) {
+        (tick, MidiMessage::Meta(MetaEvent::SetTempo(tempo)))
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn seconds_accumulate_across_tempo_changes() {
+        let file = file_with_tracks(480, vec![vec![tempo(960, 250_000), tempo(1920, 1_000_000)]]);
+        let map = file.tempo_map();
+        assert_close(map.ticks_to_seconds(0), 0.0);
+        // 120 BPM until the first change, then 240 BPM, then 60 BPM
+        assert_close(map.ticks_to_seconds(480), 0.5);
+        assert_close(map.ticks_to_seconds(960), 1.0);
+        assert_close(map.ticks_to_seconds(1440), 1.25);
+        assert_close(map.ticks_to_seconds(1920), 1.5);
+        assert_close(map.ticks_to_seconds(2400), 2.5);
+        assert_close(map.bpm_at(1000), 240.0);
+    }
+
+    #[test]
+    fn tempo_changes_in_other_tracks_apply() {
+        let file = file_with_tracks(96, vec![vec![], vec![tempo(96, 1_000_000)]]);
+        assert_close(file.tempo_map().ticks_to_seconds(192), 1.5);
+    }
+
+    #[test]
+    fn smpte_timing_ignores_tempo() {
+        // 25 frames per second, 40 ticks per frame
+        let file = file_with_tracks(0xE728, vec![vec![tempo(0, 1_000_000)]]);
+        assert_close(file.tempo_map().ticks_to_seconds(1000), 1.0);
+        assert_close(file.tempo_map().ticks_to_seconds(2500), 2.5);
+    }
+
+    #[test]
+    fn duration_is_the_longest_track() {
+        let end = |tick| (tick, MidiMessage::Meta(MetaEvent::EndOfTrack));
+        let file = file_with_tracks(
+            480,
+            vec![
+                vec![tempo(0, 1_000_000), end(480)],
+                vec![end(1920)],
+                vec![end(960)],
+            ],
+        );
+        assert_eq!(file.duration_ticks(), 1920);
+        // 60 BPM: one second per quarter note
+        assert_close(file.duration_seconds(), 4.0);
+    }
+
+    #[test]
+    fn duration_defaults_to_120_bpm() {
+        let file = file_with_tracks(
+            96,
+            vec![vec![(384, MidiMessage::Meta(MetaEvent::EndOfTrack))]],
+        );
+        assert_close(file.duration_seconds(), 2.0);
+    }
+
+    #[test]
+    fn empty_file_has_no_duration() {
+        let file = file_with_tracks(96, vec![]);
+        assert_eq!(file.duration_ticks(), 0);
+        assert_close(file.duration_seconds(), 0.0);
+        let file = file_with_tracks(96, vec![vec![]]);
+        assert_eq!(file.duration_ticks(), 0);
+    }
+
+    fn time_signature(tick: u64, numerator: u8, denominator: u8) -> (u64, MidiMessage) {
+        (
+            tick,
+            MidiMessage::Meta(MetaEvent::TimeSignature {
+                numerator,
+                denominator,
+                clocks_per_metronome: 24,
+                thirty_seconds_per_quarter: 8,
+            }),
+        )
+    }
+
+    #[test]
+    fn bars_count_across_time_signature_changes() {
+        // Two bars of 3/4, then 4/4
+        let file = file_with_tracks(
+            480,
+            vec![vec![time_signature(0, 3, 4), time_signature(2880, 4, 4)]],
+        );
+        assert_eq!(file.musical_position(0), (1, 1, 0));
+        assert_eq!(file.musical_position(1500), (2, 1, 60));
+        assert_eq!(file.musical_position(2400), (2, 3, 0));
+        assert_eq!(file.musical_position(2880), (3, 1, 0));
+        assert_eq!(file.musical_position(4320), (3, 4, 0));
+        assert_eq!(file.musical_position(4800), (4, 1, 0));
+        assert_eq!(file.musical_position(5290), (4, 2, 10));
+    }
+
+    #[test]
+    fn beats_follow_the_denominator() {
+        // 6/8 counts eighth notes, and 4/4 applies until the first change
+        let file = file_with_tracks(480, vec![vec![time_signature(1920, 6, 8)]]);
+        assert_eq!(file.musical_position(1919), (1, 4, 479));
+        assert_eq!(file.musical_position(1920 + 240 * 7), (3, 2, 0));
+    }
+
+    #[test]
+    fn change_mid_bar_starts_a_new_bar() {
+        let file = file_with_tracks(480, vec![vec![time_signature(2000, 3, 4)]]);
+        assert_eq!(file.musical_position(1999), (2, 1, 79));
+        assert_eq!(file.musical_position(2000), (3, 1, 0));
+    }
+
+    #[test]
+    fn doubling_speed_halves_duration() {
+        let end = (1920, MidiMessage::Meta(MetaEvent::EndOfTrack));
+        let mut file = file_with_tracks(
+            480,
+            vec![vec![tempo(0, 500_000), tempo(960, 1_000_000), end]],
+        );
+        let before = file.duration_seconds();
+        file.scale_tempo(2.0).unwrap();
+        assert_close(file.duration_seconds(), before / 2.0);
+        assert_eq!(
+            file.tracks[0].events[1].message,
+            MidiMessage::Meta(MetaEvent::SetTempo(500_000))
+        );
+    }
+
+    #[test]
+    fn scaling_without_tempo_events_inserts_one() {
+        let end = (960, MidiMessage::Meta(MetaEvent::EndOfTrack));
+        let mut file = file_with_tracks(480, vec![vec![end.clone()], vec![end]]);
+        file.scale_tempo(0.5).unwrap();
+        assert_eq!(
+            file.tracks[0].events[0],
+            MidiEvent {
+                delta_time: 0,
+                message: MidiMessage::Meta(MetaEvent::SetTempo(1_000_000)),
+            }
+        );
+        assert_close(file.duration_seconds(), 2.0);
+    }
+
+    #[test]
+    fn scaled_tempo_is_clamped() {
+        let scaled = |tempo_value, factor| {
+            let mut file = file_with_tracks(480, vec![vec![tempo(0, tempo_value)]]);
+            file.scale_tempo(factor).unwrap();
+            file.tracks[0].events[0].message.clone()
+        };
+        assert_eq!(
+            scaled(100, 1000.0),
+            MidiMessage::Meta(MetaEvent::SetTempo(1))
+        );
+        assert_eq!(
+            scaled(0x80_0000, 0.001),
+            MidiMessage::Meta(MetaEvent::SetTempo(MAX_TEMPO))
+        );
+    }
+
+    #[test]
+    fn bad_scale_factors_change_nothing() {
+        let mut file = file_with_tracks(480, vec![vec![tempo(0, 500_000)]]);
+        for factor in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                file.scale_tempo(factor),
+                Err(MidiError::InvalidOperation(_))
+            ));
+        }
+        assert_eq!(
+            file.tracks[0].events[0].message,
+            MidiMessage::Meta(MetaEvent::SetTempo(500_000))
+        );
+    }
+}
@@ -0,0 +1,800 @@
+use crate::timing::round;
+use crate::{
+    Channel, MetaEvent, MidiError, MidiEvent, MidiFile, MidiMessage, MidiTrack, GM_DRUM_CHANNEL,
+};
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+/// Change in controller value that `thin_controller` always keeps, however close in time
+pub const SIGNIFICANT_CC_CHANGE: u8 = 8;
+
+impl MidiFile {
+    /// Shift every note by `semitones`, clamping results to the valid 0-127 range
+    pub fn transpose(&mut self, semitones: i8) {
+        for track in &mut self.tracks {
+            track.transpose(semitones, false);
+        }
+    }
+
+    /// Like `transpose`, but leaves General MIDI percussion on channel 10 untouched
+    pub fn transpose_skipping_drums(&mut self, semitones: i8) {
+        for track in &mut self.tracks {
+            track.transpose(semitones, true);
+        }
+    }
+
+    /// Copy of the file keeping only channel messages on `channel` in every track
+    pub fn filter_channel(&self, channel: Channel) -> MidiFile {
+        MidiFile {
+            header: self.header.clone(),
+            tracks: self
+                .tracks
+                .iter()
+                .map(|track| track.filter_channel(channel))
+                .collect(),
+            chunks: self.chunks.clone(),
+        }
+    }
+
+    /// Rearrange the tracks so that track `i` becomes the one previously at `order[i]`.
+    ///
+    /// `order` must name every track exactly once, so no track can be dropped or
+    /// duplicated. Fails, leaving the file unchanged, for a format-0 file or an
+    /// incomplete permutation.
+    pub fn reorder_tracks(&mut self, order: &[usize]) -> Result<(), MidiError> {
+        self.check_reorderable()?;
+        let mut seen = vec![false; self.tracks.len()];
+        let is_permutation = order.len() == self.tracks.len()
+            && order
+                .iter()
+                .all(|&index| index < seen.len() && !core::mem::replace(&mut seen[index], true));
+        if !is_permutation {
+            return Err(MidiError::InvalidOperation(format!(
+                "Track order {:?} is not a permutation of 0..{}",
+                order,
+                self.tracks.len()
+            )));
+        }
+
+        let mut tracks: Vec<Option<MidiTrack>> = self.tracks.drain(..).map(Some).collect();
+        self.tracks = order
+            .iter()
+            .filter_map(|&index| tracks[index].take())
+            .collect();
+        Ok(())
+    }
+
+    /// Exchange tracks `a` and `b`. Fails for a format-0 file or an index out of range.
+    pub fn swap_tracks(&mut self, a: usize, b: usize) -> Result<(), MidiError> {
+        self.check_reorderable()?;
+        let len = self.tracks.len();
+        if a >= len || b >= len {
+            return Err(MidiError::InvalidOperation(format!(
+                "Cannot swap tracks {} and {} of a file with {} tracks",
+                a, b, len
+            )));
+        }
+        self.tracks.swap(a, b);
+        Ok(())
+    }
+
+    /// Reject reordering the single track of a format-0 file
+    fn check_reorderable(&self) -> Result<(), MidiError> {
+        if self.header.format == 0 {
+            return Err(MidiError::InvalidOperation(
+                "A format-0 file has a single track and cannot be reordered".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl MidiTrack {
+    /// Shift the note number of NoteOn, NoteOff and PolyphonicKeyPressure messages,
+    /// clamping to 0-127 and optionally skipping the drum channel
+    pub fn transpose(&mut self, semitones: i8, skip_drums: bool) {
+        for event in &mut self.events {
+            match &mut event.message {
+                MidiMessage::NoteOn { channel, note, .. }
+                | MidiMessage::NoteOff { channel, note, .. }
+                | MidiMessage::PolyphonicKeyPressure { channel, note, .. } => {
+                    if skip_drums && *channel == GM_DRUM_CHANNEL {
+                        continue;
+                    }
+                    *note = (*note as i16 + semitones as i16).clamp(0, 127) as u8;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Replace the velocity of every NoteOn that starts a note with `f(velocity)`, clamped
+    /// to 1-127 so a note never turns into a NoteOff. NoteOns with velocity 0 (note ends)
+    /// and NoteOff release velocities are left alone.
+    pub fn map_velocity<F: FnMut(u8) -> u8>(&mut self, mut f: F) {
+        for event in &mut self.events {
+            if let MidiMessage::NoteOn { velocity, .. } = &mut event.message {
+                if *velocity > 0 {
+                    *velocity = f(*velocity).clamp(1, 127);
+                }
+            }
+        }
+    }
+
+    /// Compress note dynamics: the part of each NoteOn velocity above `threshold` is
+    /// divided by `ratio`, so a ratio of 2 halves how far loud notes rise above it.
+    /// A ratio that is not positive leaves the track unchanged.
+    pub fn compress(&mut self, threshold: u8, ratio: f64) {
+        if ratio.is_nan() || ratio <= 0.0 {
+            return;
+        }
+        self.map_velocity(|velocity| {
+            if velocity <= threshold {
+                return velocity;
+            }
+            let excess = (velocity - threshold) as f64 / ratio;
+            round(threshold as f64 + excess).min(127.0) as u8
+        });
+    }
+
+    /// Move every channel message from channel `c` to `map[c]`, indexing `map` by
+    /// zero-based channel. Meta, SysEx and system messages are untouched.
+    pub fn remap_channels(&mut self, map: &[Channel; 16]) {
+        for event in &mut self.events {
+            if let Some(channel) = event.message.channel_mut() {
+                *channel = map[channel.get() as usize];
+            }
+        }
+    }
+
+    /// Snap event times to the nearest multiple of `grid_ticks` and recompute delta times.
+    ///
+    /// With `note_starts_only`, only NoteOn events (velocity > 0) are moved. Event order
+    /// is preserved: an event is never moved before the one preceding it, so a snapped
+    /// event can drag later unsnapped events along with it.
+    pub fn quantize(&mut self, grid_ticks: u32, note_starts_only: bool) {
+        if grid_ticks == 0 {
+            return;
+        }
+        let grid = grid_ticks as u64;
+
+        let mut previous_tick = 0;
+        let quantized: Vec<(u64, MidiMessage)> = self
+            .iter_absolute()
+            .map(|(tick, event)| {
+                let is_note_start =
+                    matches!(event.message, MidiMessage::NoteOn { velocity, .. } if velocity > 0);
+                let target = if note_starts_only && !is_note_start {
+                    tick
+                } else {
+                    (tick + grid / 2) / grid * grid
+                };
+                previous_tick = target.max(previous_tick);
+                (previous_tick, event.message.clone())
+            })
+            .collect();
+
+        *self = MidiTrack::from_absolute(quantized);
+    }
+
+    /// Insert `message` at `absolute_tick`, after any events already at that tick, and
+    /// adjust delta times so every other event keeps its absolute position.
+    ///
+    /// A trailing EndOfTrack stays last: an insertion past it moves it to the new tick.
+    pub fn insert_event_at(&mut self, absolute_tick: u64, message: MidiMessage) {
+        let mut index = 0;
+        let mut previous_tick = 0;
+        for (tick, event) in self.iter_absolute() {
+            let is_last_end_of_track =
+                index + 1 == self.events.len() && event.message.is_end_of_track();
+            if tick > absolute_tick || is_last_end_of_track {
+                break;
+            }
+            previous_tick = tick;
+            index += 1;
+        }
+
+        let delta = |ticks: u64| u32::try_from(ticks).unwrap_or(u32::MAX);
+        if let Some(next) = self.events.get_mut(index) {
+            let next_tick = previous_tick + next.delta_time as u64;
+            next.delta_time = delta(next_tick.saturating_sub(absolute_tick));
+        }
+        self.events.insert(
+            index,
+            MidiEvent {
+                delta_time: delta(absolute_tick - previous_tick),
+                message,
+            },
+        );
+    }
+
+    /// Remove every event whose absolute tick is in `start_tick..end_tick`, except a
+    /// trailing EndOfTrack.
+    ///
+    /// Without `ripple`, the remaining events keep their absolute positions and a gap is
+    /// left. With `ripple`, events after the range move earlier by its length to close
+    /// the gap (a kept EndOfTrack inside the range moves to `start_tick`).
+    pub fn delete_range(&mut self, start_tick: u64, end_tick: u64, ripple: bool) {
+        if start_tick >= end_tick {
+            return;
+        }
+        let span = end_tick - start_tick;
+        let last = self.events.len().saturating_sub(1);
+
+        let kept: Vec<(u64, MidiMessage)> = self
+            .iter_absolute()
+            .enumerate()
+            .filter_map(|(index, (tick, event))| {
+                let is_end_of_track = index == last && event.message.is_end_of_track();
+                let tick = if tick < start_tick {
+                    tick
+                } else if tick >= end_tick {
+                    if ripple {
+                        tick - span
+                    } else {
+                        tick
+                    }
+                } else if is_end_of_track {
+                    if ripple {
+                        start_tick
+                    } else {
+                        tick
+                    }
+                } else {
+                    return None;
+                };
+                Some((tick, event.message.clone()))
+            })
+            .collect();
+
+        *self = MidiTrack::from_absolute(kept);
+    }
+
+    /// Move a trailing EndOfTrack back to `margin` ticks after the last other event,
+    /// cutting off silence at the end of the track.
+    ///
+    /// Only the EndOfTrack moves, and never later than it was; a track that does not end
+    /// with one is left alone.
+    pub fn trim_trailing_silence(&mut self, margin: u32) {
+        // The EndOfTrack's delta time is the silence after the last event
+        if let Some(
+            end_of_track @ MidiEvent {
+                message: MidiMessage::Meta(MetaEvent::EndOfTrack),
+                ..
+            },
+        ) = self.events.last_mut()
+        {
+            end_of_track.delta_time = end_of_track.delta_time.min(margin);
+        }
+    }
+
+    /// Thin dense automation of one controller by dropping ControlChange events that come
+    /// less than `min_tick_gap` ticks after the last one kept on the same channel.
+    ///
+    /// An event is kept anyway when its value differs from the last kept one by at least
+    /// `SIGNIFICANT_CC_CHANGE`, or when it ends a dense run (the channel's next event for
+    /// the controller is at least the gap away, or there is none), so jumps and the value
+    /// held afterwards survive. Everything left keeps its absolute position.
+    pub fn thin_controller(&mut self, controller: u8, min_tick_gap: u32) {
+        let gap = min_tick_gap as u64;
+        let points: Vec<Option<(usize, u64, u8)>> = self
+            .iter_absolute()
+            .map(|(tick, event)| match event.message {
+                MidiMessage::ControlChange {
+                    channel,
+                    controller: c,
+                    value,
+                } if c == controller => Some((channel.get() as usize, tick, value)),
+                _ => None,
+            })
+            .collect();
+
+        // Tick of the next matching event on the same channel
+        let mut next_tick = vec![None; points.len()];
+        let mut following: [Option<u64>; 16] = [None; 16];
+        for (index, point) in points.iter().enumerate().rev() {
+            if let Some((channel, tick, _)) = *point {
+                next_tick[index] = following[channel];
+                following[channel] = Some(tick);
+            }
+        }
+
+        let mut keep = vec![true; points.len()];
+        let mut last_kept: [Option<(u64, u8)>; 16] = [None; 16];
+        for (index, point) in points.iter().enumerate() {
+            let Some((channel, tick, value)) = *point else {
+                continue;
+            };
+            keep[index] = match last_kept[channel] {
+                None => true,
+                Some((kept_tick, kept_value)) => {
+                    tick - kept_tick >= gap
+                        || value.abs_diff(kept_value) >= SIGNIFICANT_CC_CHANGE
+                        || next_tick[index].is_none_or(|next| next - tick >= gap)
+                }
+            };
+            if keep[index] {
+                last_kept[channel] = Some((tick, value));
+            }
+        }
+
+        let mut keep = keep.into_iter();
+        self.retain(|_| keep.next().unwrap_or(true));
+    }
+
+    /// Keep only the events for which `f` returns true, like `Vec::retain`.
+    ///
+    /// The delta times of removed events are folded into the next surviving event, so
+    /// everything that remains keeps its absolute tick position.
+    pub fn retain<F: FnMut(&MidiEvent) -> bool>(&mut self, mut f: F) {
+        let mut carried = 0u64;
+        self.events.retain_mut(|event| {
+            carried += event.delta_time as u64;
+            if !f(event) {
+                return false;
+            }
+            event.delta_time = u32::try_from(carried).unwrap_or(u32::MAX);
+            carried = 0;
+            true
+        });
+    }
+
+    /// Copy of the track keeping only channel messages on `channel`, plus every event
+    /// without a channel (meta, SysEx, system). Surviving events keep their timing.
+    pub fn filter_channel(&self, channel: Channel) -> MidiTrack {
+        MidiTrack::from_absolute(
+            self.iter_absolute()
+                .filter(|(_, event)| event.message.channel().is_none_or(|c| c == channel))
+                .map(|(tick, event)| (tick, event.message.clone())),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MidiHeader;
+
+    fn on(channel: u8, note: u8, velocity: u8) -> MidiMessage {
+        MidiMessage::NoteOn {
+            channel: Channel::new(channel).unwrap(),
+            note,
+            velocity,
+        }
+    }
+
+    fn off(channel: u8, note: u8) -> MidiMessage {
+        MidiMessage::NoteOff {
+            channel: Channel::new(channel).unwrap(),
+            note,
+            velocity: 64,
+        }
+    }
+
+    const END_OF_TRACK: MidiMessage = MidiMessage::Meta(MetaEvent::EndOfTrack);
+
+    /// Messages of a track paired with their absolute ticks
+    fn absolute(track: &MidiTrack) -> Vec<(u64, MidiMessage)> {
+        track
+            .iter_absolute()
+            .map(|(tick, event)| (tick, event.message.clone()))
+            .collect()
+    }
+
+    fn file_with_track(events: Vec<(u64, MidiMessage)>) -> MidiFile {
+        MidiFile {
+            header: MidiHeader {
+                format: 0,
+                num_tracks: 1,
+                time_division: 96,
+            },
+            tracks: vec![MidiTrack::from_absolute(events)],
+            chunks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn transpose_raises_every_note_by_an_octave() {
+        let pressure = |note| MidiMessage::PolyphonicKeyPressure {
+            channel: Channel::new(2).unwrap(),
+            note,
+            pressure: 30,
+        };
+        let mut file = file_with_track(vec![
+            (0, on(0, 60, 100)),
+            (0, pressure(64)),
+            (10, off(0, 60)),
+            (10, on(2, 120, 100)),
+            (20, MidiMessage::Meta(MetaEvent::SetTempo(500_000))),
+            (20, END_OF_TRACK),
+        ]);
+        file.transpose(12);
+        assert_eq!(
+            absolute(&file.tracks[0]),
+            [
+                (0, on(0, 72, 100)),
+                (0, pressure(76)),
+                (10, off(0, 72)),
+                // Clamped rather than wrapped
+                (10, on(2, 127, 100)),
+                (20, MidiMessage::Meta(MetaEvent::SetTempo(500_000))),
+                (20, END_OF_TRACK),
+            ]
+        );
+
+        file.transpose(-100);
+        assert_eq!(file.tracks[0].events[0].message, on(0, 0, 100));
+    }
+
+    #[test]
+    fn transpose_can_skip_drums() {
+        let mut file = file_with_track(vec![(0, on(9, 36, 100)), (0, on(0, 60, 100))]);
+        file.transpose_skipping_drums(2);
+        assert_eq!(
+            absolute(&file.tracks[0]),
+            [(0, on(9, 36, 100)), (0, on(0, 62, 100))]
+        );
+    }
+
+    #[test]
+    fn quantize_aligns_jittered_note_starts() {
+        let mut track = MidiTrack::from_absolute([
+            (3, on(0, 60, 100)),
+            (40, off(0, 60)),
+            (95, on(0, 62, 100)),
+            (150, off(0, 62)),
+            (190, on(0, 64, 100)),
+            (291, on(0, 65, 100)),
+            (300, END_OF_TRACK),
+        ]);
+        let mut all_events = track.clone();
+
+        track.quantize(96, true);
+        assert_eq!(
+            absolute(&track),
+            [
+                (0, on(0, 60, 100)),
+                (40, off(0, 60)),
+                (96, on(0, 62, 100)),
+                (150, off(0, 62)),
+                (192, on(0, 64, 100)),
+                (288, on(0, 65, 100)),
+                (300, END_OF_TRACK),
+            ]
+        );
+
+        all_events.quantize(96, false);
+        assert_eq!(all_events.absolute_times(), [0, 0, 96, 192, 192, 288, 288]);
+    }
+
+    #[test]
+    fn quantize_never_reorders_events() {
+        // The NoteOn snaps forward past the unsnapped NoteOff, which has to follow it
+        let mut track =
+            MidiTrack::from_absolute([(50, on(0, 60, 100)), (60, off(0, 60)), (60, END_OF_TRACK)]);
+        track.quantize(96, true);
+        assert_eq!(
+            absolute(&track),
+            [(96, on(0, 60, 100)), (96, off(0, 60)), (96, END_OF_TRACK)]
+        );
+    }
+
+    #[test]
+    fn removing_pitch_bends_keeps_note_timing() {
+        let bend = |value| MidiMessage::PitchBendChange {
+            channel: Channel::new(0).unwrap(),
+            value,
+        };
+        let mut track = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (10, bend(100)),
+            (20, bend(200)),
+            (30, off(0, 60)),
+            (40, bend(0)),
+            (50, on(0, 62, 100)),
+            (60, bend(0)),
+            (60, END_OF_TRACK),
+        ]);
+        track.retain(|event| !matches!(event.message, MidiMessage::PitchBendChange { .. }));
+        assert_eq!(
+            absolute(&track),
+            [
+                (0, on(0, 60, 100)),
+                (30, off(0, 60)),
+                (50, on(0, 62, 100)),
+                (60, END_OF_TRACK),
+            ]
+        );
+    }
+
+    #[test]
+    fn remap_moves_channel_0_to_5() {
+        let mut map: [Channel; 16] = core::array::from_fn(|c| Channel::new(c as u8).unwrap());
+        map[0] = Channel::new(5).unwrap();
+        let mut track = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (0, on(1, 62, 100)),
+            (
+                5,
+                MidiMessage::ControlChange {
+                    channel: Channel::new(0).unwrap(),
+                    controller: 7,
+                    value: 90,
+                },
+            ),
+            (6, MidiMessage::SysEx(vec![0x7E, 0xF7])),
+            (7, MidiMessage::Meta(MetaEvent::ChannelPrefix(0))),
+            (10, off(0, 60)),
+            (10, END_OF_TRACK),
+        ]);
+        track.remap_channels(&map);
+        assert_eq!(
+            absolute(&track),
+            [
+                (0, on(5, 60, 100)),
+                (0, on(1, 62, 100)),
+                (
+                    5,
+                    MidiMessage::ControlChange {
+                        channel: Channel::new(5).unwrap(),
+                        controller: 7,
+                        value: 90,
+                    },
+                ),
+                (6, MidiMessage::SysEx(vec![0x7E, 0xF7])),
+                (7, MidiMessage::Meta(MetaEvent::ChannelPrefix(0))),
+                (10, off(5, 60)),
+                (10, END_OF_TRACK),
+            ]
+        );
+    }
+
+    #[test]
+    fn halving_velocities_clamps_to_1() {
+        let mut track = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (0, on(0, 62, 127)),
+            (0, on(0, 64, 1)),
+            (10, on(0, 60, 0)),
+            (10, off(0, 62)),
+        ]);
+        track.map_velocity(|velocity| velocity / 2);
+        assert_eq!(
+            absolute(&track),
+            [
+                (0, on(0, 60, 50)),
+                (0, on(0, 62, 63)),
+                (0, on(0, 64, 1)),
+                // Note ends keep their velocities
+                (10, on(0, 60, 0)),
+                (10, off(0, 62)),
+            ]
+        );
+
+        track.map_velocity(|_| 200);
+        assert_eq!(track.events[0].message, on(0, 60, 127));
+    }
+
+    #[test]
+    fn compress_reduces_velocities_above_the_threshold() {
+        let mut track = MidiTrack::from_absolute([
+            (0, on(0, 60, 50)),
+            (0, on(0, 62, 100)),
+            (0, on(0, 64, 127)),
+        ]);
+        track.compress(64, 2.0);
+        assert_eq!(
+            absolute(&track),
+            [(0, on(0, 60, 50)), (0, on(0, 62, 82)), (0, on(0, 64, 96))]
+        );
+
+        let before = track.clone();
+        track.compress(64, 0.0);
+        assert_eq!(track, before);
+    }
+
+    #[test]
+    fn inserted_tempo_leaves_later_events_in_place() {
+        let tempo = MidiMessage::Meta(MetaEvent::SetTempo(400_000));
+        let mut track = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (100, off(0, 60)),
+            (100, on(0, 62, 100)),
+            (250, off(0, 62)),
+            (300, END_OF_TRACK),
+        ]);
+        track.insert_event_at(100, tempo.clone());
+        track.insert_event_at(180, tempo.clone());
+        assert_eq!(
+            absolute(&track),
+            [
+                (0, on(0, 60, 100)),
+                (100, off(0, 60)),
+                (100, on(0, 62, 100)),
+                // After the events already on the tick
+                (100, tempo.clone()),
+                (180, tempo.clone()),
+                (250, off(0, 62)),
+                (300, END_OF_TRACK),
+            ]
+        );
+        assert_eq!(track.events[4].delta_time, 80);
+        assert_eq!(track.events[5].delta_time, 70);
+    }
+
+    #[test]
+    fn insertion_past_the_end_moves_end_of_track() {
+        let mut track = MidiTrack::from_absolute([(0, on(0, 60, 100)), (50, END_OF_TRACK)]);
+        track.insert_event_at(80, off(0, 60));
+        track.insert_event_at(50, on(0, 62, 100));
+        assert_eq!(
+            absolute(&track),
+            [
+                (0, on(0, 60, 100)),
+                (50, on(0, 62, 100)),
+                (80, off(0, 60)),
+                (80, END_OF_TRACK),
+            ]
+        );
+    }
+
+    fn edited_track() -> MidiTrack {
+        MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (10, off(0, 60)),
+            (20, on(0, 62, 100)),
+            (30, off(0, 62)),
+            (40, on(0, 64, 100)),
+            (50, off(0, 64)),
+            (60, END_OF_TRACK),
+        ])
+    }
+
+    #[test]
+    fn delete_range_leaves_other_events_in_place() {
+        let mut track = edited_track();
+        track.delete_range(20, 40, false);
+        assert_eq!(
+            absolute(&track),
+            [
+                (0, on(0, 60, 100)),
+                (10, off(0, 60)),
+                (40, on(0, 64, 100)),
+                (50, off(0, 64)),
+                (60, END_OF_TRACK),
+            ]
+        );
+    }
+
+    #[test]
+    fn ripple_delete_closes_the_gap() {
+        let mut track = edited_track();
+        track.delete_range(20, 40, true);
+        assert_eq!(
+            absolute(&track),
+            [
+                (0, on(0, 60, 100)),
+                (10, off(0, 60)),
+                (20, on(0, 64, 100)),
+                (30, off(0, 64)),
+                (40, END_OF_TRACK),
+            ]
+        );
+    }
+
+    #[test]
+    fn delete_range_keeps_the_final_end_of_track() {
+        let mut track = edited_track();
+        track.delete_range(40, 100, true);
+        assert_eq!(
+            absolute(&track)[3..],
+            [(30, off(0, 62)), (40, END_OF_TRACK)]
+        );
+
+        let mut empty_range = edited_track();
+        empty_range.delete_range(30, 30, true);
+        assert_eq!(empty_range, edited_track());
+    }
+
+    fn expression(tick: u64, value: u8) -> (u64, MidiMessage) {
+        (
+            tick,
+            MidiMessage::ControlChange {
+                channel: Channel::new(0).unwrap(),
+                controller: 11,
+                value,
+            },
+        )
+    }
+
+    #[test]
+    fn thinning_drops_dense_near_identical_events() {
+        let mut events: Vec<(u64, MidiMessage)> = (0..1000)
+            .map(|i| expression(i, 64 + (i % 3) as u8))
+            .collect();
+        events.push((500, on(0, 60, 100)));
+        events.sort_by_key(|(tick, _)| *tick);
+        events.push((1000, END_OF_TRACK));
+        let mut track = MidiTrack::from_absolute(events);
+
+        track.thin_controller(11, 10);
+        let kept: Vec<(u64, MidiMessage)> = absolute(&track)
+            .into_iter()
+            .filter(|(_, m)| matches!(m, MidiMessage::ControlChange { .. }))
+            .collect();
+        assert_eq!(kept.len(), 101);
+        assert_eq!(kept[0], expression(0, 64));
+        assert_eq!(kept[1], expression(10, 65));
+        // The last value of the run is always kept
+        assert_eq!(kept[100], expression(999, 64));
+        assert!(absolute(&track).contains(&(500, on(0, 60, 100))));
+        assert_eq!(track.absolute_times().last(), Some(&1000));
+    }
+
+    #[test]
+    fn thinning_keeps_jumps_and_other_controllers() {
+        let volume = |tick, value| {
+            (
+                tick,
+                MidiMessage::ControlChange {
+                    channel: Channel::new(0).unwrap(),
+                    controller: 7,
+                    value,
+                },
+            )
+        };
+        let mut track = MidiTrack::from_absolute([
+            expression(0, 64),
+            volume(1, 100),
+            expression(1, 65),
+            expression(2, 100),
+            volume(2, 101),
+            expression(3, 101),
+            expression(50, 100),
+        ]);
+        track.thin_controller(11, 10);
+        assert_eq!(
+            absolute(&track),
+            [
+                expression(0, 64),
+                volume(1, 100),
+                expression(2, 100),
+                volume(2, 101),
+                expression(3, 101),
+                expression(50, 100),
+            ]
+        );
+    }
+
+    #[test]
+    fn trimming_shortens_padded_tracks() {
+        let padded = MidiTrack::from_absolute([
+            (0, on(0, 60, 100)),
+            (96, off(0, 60)),
+            expression(100, 0),
+            (2000, END_OF_TRACK),
+        ]);
+        let mut track = padded.clone();
+        track.trim_trailing_silence(0);
+        assert_eq!(track.absolute_times(), [0, 96, 100, 100]);
+
+        let mut with_margin = padded.clone();
+        with_margin.trim_trailing_silence(48);
+        assert_eq!(with_margin.absolute_times().last(), Some(&148));
+        assert_eq!(with_margin.events[..3], padded.events[..3]);
+
+        // Never lengthens the track
+        let mut short = padded;
+        short.trim_trailing_silence(5000);
+        assert_eq!(short.absolute_times().last(), Some(&2000));
+    }
+
+    #[test]
+    fn trimming_needs_a_final_end_of_track() {
+        let track = MidiTrack::from_absolute([(0, on(0, 60, 100)), (500, off(0, 60))]);
+        let mut trimmed = track.clone();
+        trimmed.trim_trailing_silence(0);
+        assert_eq!(trimmed, track);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::{Channel, MidiFile, MidiTrack};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A structural problem found in a parsed or constructed MIDI file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// The track's last event is not an EndOfTrack meta event
+    MissingEndOfTrack { track: usize },
+    /// The header's track count disagrees with the number of tracks present
+    TrackCountMismatch { declared: u16, actual: usize },
+    /// A NoteOn is never followed by a matching NoteOff
+    UnclosedNote {
+        track: usize,
+        channel: Channel,
+        note: u8,
+        start_tick: u64,
+    },
+    /// A format-0 file must contain exactly one track
+    Format0TrackCount { tracks: usize },
+    /// An EndOfTrack appears before the last event, so readers stop early
+    EarlyEndOfTrack { track: usize, index: usize },
+    /// Track 0 of a multi-track format-1 file, by convention the conductor track for
+    /// tempo and other meta events, holds channel messages such as notes
+    NotesOnConductorTrack { indices: Vec<usize> }, // Indices of the channel messages
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationWarning::MissingEndOfTrack { track } => {
+                write!(f, "Track {}: does not end with EndOfTrack", track)
+            }
+            ValidationWarning::TrackCountMismatch { declared, actual } => write!(
+                f,
+                "Header declares {} tracks but file has {}",
+                declared, actual
+            ),
+            ValidationWarning::UnclosedNote {
+                track,
+                channel,
+                note,
+                start_tick,
+            } => write!(
+                f,
+                "Track {}: note {} on channel {} at tick {} is never released",
+                track, note, channel, start_tick
+            ),
+            ValidationWarning::Format0TrackCount { tracks } => {
+                write!(f, "Format 0 file has {} tracks, expected 1", tracks)
+            }
+            ValidationWarning::EarlyEndOfTrack { track, index } => write!(
+                f,
+                "Track {}: EndOfTrack at event {} is followed by more events",
+                track, index
+            ),
+            ValidationWarning::NotesOnConductorTrack { indices } => write!(
+                f,
+                "Track 0: conductor track holds {} channel messages, first at event {}",
+                indices.len(),
+                indices.first().copied().unwrap_or_default()
+            ),
+        }
+    }
+}
+
+impl MidiTrack {
+    /// Warnings for EndOfTrack events that are not the track's last event
+    pub(crate) fn early_end_of_track(
+        &self,
+        track: usize,
+    ) -> impl Iterator<Item = ValidationWarning> + '_ {
+        let last = self.events.len().saturating_sub(1);
+        self.events
+            .iter()
+            .enumerate()
+            .filter(move |&(index, event)| index < last && event.message.is_end_of_track())
+            .map(move |(index, _)| ValidationWarning::EarlyEndOfTrack { track, index })
+    }
+}
+
+impl MidiFile {
+    /// Check the file for structural problems without failing
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        if self.header.num_tracks as usize != self.tracks.len() {
+            warnings.push(ValidationWarning::TrackCountMismatch {
+                declared: self.header.num_tracks,
+                actual: self.tracks.len(),
+            });
+        }
+
+        if self.header.format == 0 && self.tracks.len() != 1 {
+            warnings.push(ValidationWarning::Format0TrackCount {
+                tracks: self.tracks.len(),
+            });
+        }
+
+        if self.header.format == 1 && self.tracks.len() > 1 {
+            let indices: Vec<usize> = self.tracks[0]
+                .events
+                .iter()
+                .enumerate()
+                .filter(|(_, event)| event.message.is_channel_message())
+                .map(|(index, _)| index)
+                .collect();
+            if !indices.is_empty() {
+                warnings.push(ValidationWarning::NotesOnConductorTrack { indices });
+            }
+        }
+
+        for (index, track) in self.tracks.iter().enumerate() {
+            if !track.ends_with_end_of_track() {
+                warnings.push(ValidationWarning::MissingEndOfTrack { track: index });
+            }
+            warnings.extend(track.early_end_of_track(index));
+
+            for note in track.unclosed_notes() {
+                warnings.push(ValidationWarning::UnclosedNote {
+                    track: index,
+                    channel: note.channel,
+                    note: note.note,
+                    start_tick: note.start_tick,
+                });
+            }
+        }
+
+        warnings
+    }
+}
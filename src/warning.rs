@@ -0,0 +1,80 @@
+use core::fmt;
+
+/// A recoverable irregularity in the input that the parser worked around
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// The MThd chunk is longer than the standard 6 bytes; the extra bytes were skipped
+    HeaderLength { length: u32 },
+    /// A track chunk's declared length runs past the end of the input; it was read to the end
+    TrackLengthPastEof {
+        offset: u64, // Start of the chunk's data
+        declared: u64,
+        available: u64,
+    },
+    /// A track chunk's declared length ends before its last event; it was read to EndOfTrack
+    TrackLengthTooShort {
+        offset: u64, // Start of the chunk's data
+        declared: u64,
+        actual: u64,
+    },
+    /// A track chunk's declared length runs past the next chunk header; the track was
+    /// ended at its EndOfTrack so the next chunk can be read
+    TrackLengthTooLong {
+        offset: u64, // Start of the chunk's data
+        declared: u64,
+        actual: u64,
+    },
+    /// The header's time division has zero ticks per quarter note or per frame; it was
+    /// replaced so times can be computed
+    ZeroTimeDivision {
+        time_division: u16,
+        replacement: u16,
+    },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseWarning::HeaderLength { length } => write!(
+                f,
+                "Header chunk is {} bytes long, expected 6; extra bytes skipped",
+                length
+            ),
+            ParseWarning::TrackLengthPastEof {
+                offset,
+                declared,
+                available,
+            } => write!(
+                f,
+                "Track chunk at byte {} declares {} bytes but only {} remain",
+                offset, declared, available
+            ),
+            ParseWarning::TrackLengthTooShort {
+                offset,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "Track chunk at byte {} declares {} bytes but its events take {}",
+                offset, declared, actual
+            ),
+            ParseWarning::TrackLengthTooLong {
+                offset,
+                declared,
+                actual,
+            } => write!(
+                f,
+                "Track chunk at byte {} declares {} bytes but ends after {}, before the next chunk",
+                offset, declared, actual
+            ),
+            ParseWarning::ZeroTimeDivision {
+                time_division,
+                replacement,
+            } => write!(
+                f,
+                "Time division 0x{:04X} has zero ticks; using {} ticks per quarter note",
+                time_division, replacement
+            ),
+        }
+    }
+}
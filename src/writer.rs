@@ -0,0 +1,336 @@
+use crate::encode::write_variable_length;
+use crate::{MetaEvent, MidiError, MidiEvent, MidiFile, MidiMessage, MidiTrack, ValidationWarning};
+use byteorder::{BigEndian, WriteBytesExt};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Choices that affect how a MidiFile is serialized
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// Omit the status byte of a channel message when it repeats the previous one.
+    /// System and meta events in between force the status to be written again.
+    pub use_running_status: bool,
+    /// Close every track whose last event is not EndOfTrack with one at delta 0
+    pub append_end_of_track: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        WriteOptions {
+            use_running_status: false,
+            append_end_of_track: true,
+        }
+    }
+}
+
+impl MidiFile {
+    /// Serialize the MIDI file to the given writer
+    pub fn write<W: Write>(&self, w: &mut W) -> Result<(), MidiError> {
+        self.write_with_options(w, &WriteOptions::default())?;
+        Ok(())
+    }
+
+    /// Serialize the MIDI file to the given writer using the given options, returning
+    /// warnings about tracks that will not read back as written (such as an EndOfTrack
+    /// before the last event)
+    pub fn write_with_options<W: Write>(
+        &self,
+        w: &mut W,
+        options: &WriteOptions,
+    ) -> Result<Vec<ValidationWarning>, MidiError> {
+        let num_tracks = u16::try_from(self.tracks.len()).map_err(|_| {
+            MidiError::Encode(format!("Too many tracks to write: {}", self.tracks.len()))
+        })?;
+
+        // Header chunk
+        w.write_all(b"MThd")?;
+        w.write_u32::<BigEndian>(6)?;
+        w.write_u16::<BigEndian>(self.header.format)?;
+        w.write_u16::<BigEndian>(num_tracks)?;
+        w.write_u16::<BigEndian>(self.header.time_division)?;
+
+        // Unrecognized chunks go before the tracks, where the parser will find them again
+        for chunk in &self.chunks {
+            let length = u32::try_from(chunk.data.len()).map_err(|_| {
+                MidiError::Encode(format!("Chunk too long: {} bytes", chunk.data.len()))
+            })?;
+            w.write_all(&chunk.id)?;
+            w.write_u32::<BigEndian>(length)?;
+            w.write_all(&chunk.data)?;
+        }
+
+        // Track chunks
+        let mut warnings = Vec::new();
+        for (index, track) in self.tracks.iter().enumerate() {
+            Self::write_track(w, track, options)?;
+            warnings.extend(track.early_end_of_track(index));
+        }
+
+        Ok(warnings)
+    }
+
+    /// Serialize the MIDI file and save it to the given path
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), MidiError> {
+        self.save_with_options(path, &WriteOptions::default())?;
+        Ok(())
+    }
+
+    /// Serialize the MIDI file with the given options and save it to the given path,
+    /// returning the same warnings as `write_with_options`
+    pub fn save_with_options<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Result<Vec<ValidationWarning>, MidiError> {
+        let mut file = File::create(path)?;
+        let warnings = self.write_with_options(&mut file, options)?;
+        file.flush()?;
+        Ok(warnings)
+    }
+
+    /// Write a single track as an MTrk chunk
+    fn write_track<W: Write>(
+        w: &mut W,
+        track: &MidiTrack,
+        options: &WriteOptions,
+    ) -> Result<(), MidiError> {
+        // Encode the events first so the chunk length is known
+        let mut data = Vec::new();
+        let mut running_status = None;
+        for event in &track.events {
+            Self::encode_event(event, &mut data, &mut running_status, options)?;
+        }
+
+        if options.append_end_of_track && !track.ends_with_end_of_track() {
+            let end_of_track = MidiEvent {
+                delta_time: 0,
+                message: MidiMessage::Meta(MetaEvent::EndOfTrack),
+            };
+            Self::encode_event(&end_of_track, &mut data, &mut running_status, options)?;
+        }
+
+        let track_length = u32::try_from(data.len())
+            .map_err(|_| MidiError::Encode(format!("Track too long: {} bytes", data.len())))?;
+
+        w.write_all(b"MTrk")?;
+        w.write_u32::<BigEndian>(track_length)?;
+        w.write_all(&data)?;
+
+        Ok(())
+    }
+
+    /// Encode a single event (delta time followed by message bytes), dropping the status
+    /// byte when running status is enabled and it repeats `running_status`
+    fn encode_event(
+        event: &MidiEvent,
+        buf: &mut Vec<u8>,
+        running_status: &mut Option<u8>,
+        options: &WriteOptions,
+    ) -> Result<(), MidiError> {
+        write_variable_length(event.delta_time, buf)?;
+
+        let message_start = buf.len();
+        event.message.encode(buf)?;
+        match event.message.status_byte() {
+            Some(status) if status < 0xF0 => {
+                if options.use_running_status && *running_status == Some(status) {
+                    buf.remove(message_start);
+                }
+                *running_status = Some(status);
+            }
+            _ => *running_status = None,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Channel;
+
+    fn event(delta_time: u32, message: MidiMessage) -> MidiEvent {
+        MidiEvent {
+            delta_time,
+            message,
+        }
+    }
+
+    fn file_with_track(events: Vec<MidiEvent>) -> MidiFile {
+        MidiFile {
+            header: crate::MidiHeader {
+                format: 0,
+                num_tracks: 1,
+                time_division: 96,
+            },
+            tracks: vec![MidiTrack { events }],
+            chunks: Vec::new(),
+        }
+    }
+
+    fn written(file: &MidiFile, options: &WriteOptions) -> Vec<u8> {
+        let mut data = Vec::new();
+        file.write_with_options(&mut data, options).unwrap();
+        data
+    }
+
+    #[test]
+    fn sample_file_round_trips() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/samples/bachinv1.mid");
+        let file = MidiFile::open(path).unwrap();
+        let mut data = Vec::new();
+        file.write(&mut data).unwrap();
+        assert_eq!(MidiFile::from_bytes(&data).unwrap(), file);
+    }
+
+    #[test]
+    fn chunks_carry_their_lengths() {
+        let file = file_with_track(vec![
+            event(0, MidiMessage::Meta(MetaEvent::SetTempo(500_000))),
+            event(200, MidiMessage::Meta(MetaEvent::EndOfTrack)),
+        ]);
+        let data = written(&file, &WriteOptions::default());
+        assert_eq!(&data[..14], b"MThd\0\0\0\x06\0\0\0\x01\0\x60");
+        assert_eq!(&data[14..22], b"MTrk\0\0\0\x0C");
+        // The 200-tick delta takes two variable-length bytes
+        assert_eq!(&data[29..], [0x81, 0x48, 0xFF, 0x2F, 0x00]);
+    }
+
+    #[test]
+    fn tempo_and_time_signature_keep_their_bytes() {
+        let mut events = vec![0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20];
+        events.extend_from_slice(&[0x00, 0xFF, 0x58, 0x04, 0x06, 0x03, 0x24, 0x08]);
+        events.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+        let mut data = b"MThd\0\0\0\x06\0\0\0\x01\0\x60MTrk".to_vec();
+        data.extend_from_slice(&(events.len() as u32).to_be_bytes());
+        data.extend_from_slice(&events);
+
+        let file = MidiFile::from_bytes(&data).unwrap();
+        assert_eq!(written(&file, &WriteOptions::default()), data);
+    }
+
+    #[test]
+    fn channel_messages_round_trip() {
+        let channel = Channel::new(9).unwrap();
+        let file = file_with_track(vec![
+            event(
+                0,
+                MidiMessage::ProgramChange {
+                    channel,
+                    program: 5,
+                },
+            ),
+            event(
+                10,
+                MidiMessage::NoteOn {
+                    channel,
+                    note: 60,
+                    velocity: 100,
+                },
+            ),
+            event(
+                0x0FFF_FFFF,
+                MidiMessage::NoteOff {
+                    channel,
+                    note: 60,
+                    velocity: 64,
+                },
+            ),
+            event(0, MidiMessage::Meta(MetaEvent::EndOfTrack)),
+        ]);
+        let data = written(&file, &WriteOptions::default());
+        assert_eq!(MidiFile::from_bytes(&data).unwrap(), file);
+    }
+
+    #[test]
+    fn running_status_output_reparses_to_the_same_events() {
+        let channel = Channel::new(0).unwrap();
+        let note = |note, velocity| MidiMessage::NoteOn {
+            channel,
+            note,
+            velocity,
+        };
+        let file = file_with_track(vec![
+            event(0, note(60, 100)),
+            event(0, note(64, 100)),
+            event(10, MidiMessage::Meta(MetaEvent::SetTempo(500_000))),
+            event(0, note(67, 100)),
+            event(0, note(72, 100)),
+            event(0, MidiMessage::Meta(MetaEvent::EndOfTrack)),
+        ]);
+        let options = WriteOptions {
+            use_running_status: true,
+            ..WriteOptions::default()
+        };
+        let compressed = written(&file, &options);
+        assert_eq!(
+            &compressed[22..],
+            [
+                0x00, 0x90, 0x3C, 0x64, 0x00, 0x40, 0x64, // Status omitted
+                0x0A, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // Meta cancels running status
+                0x00, 0x90, 0x43, 0x64, 0x00, 0x48, 0x64, 0x00, 0xFF, 0x2F, 0x00,
+            ]
+        );
+        assert_eq!(
+            compressed.len() + 2,
+            written(&file, &WriteOptions::default()).len()
+        );
+        assert_eq!(MidiFile::from_bytes(&compressed).unwrap(), file);
+    }
+
+    #[test]
+    fn missing_end_of_track_is_appended() {
+        let note = MidiMessage::NoteOn {
+            channel: Channel::new(0).unwrap(),
+            note: 60,
+            velocity: 100,
+        };
+        let file = file_with_track(vec![event(0, note.clone())]);
+        let data = written(&file, &WriteOptions::default());
+        assert_eq!(
+            &data[22..],
+            [0x00, 0x90, 0x3C, 0x64, 0x00, 0xFF, 0x2F, 0x00]
+        );
+
+        let track = &MidiFile::from_bytes(&data).unwrap().tracks[0];
+        assert_eq!(
+            track.events,
+            [
+                event(0, note),
+                event(0, MidiMessage::Meta(MetaEvent::EndOfTrack))
+            ]
+        );
+    }
+
+    #[test]
+    fn end_of_track_is_not_appended_when_disabled() {
+        let file = file_with_track(vec![event(0, MidiMessage::TuneRequest)]);
+        let options = WriteOptions {
+            append_end_of_track: false,
+            ..WriteOptions::default()
+        };
+        assert_eq!(&written(&file, &options)[18..], [0, 0, 0, 2, 0x00, 0xF6]);
+    }
+
+    #[test]
+    fn end_of_track_mid_track_is_reported() {
+        let file = file_with_track(vec![
+            event(0, MidiMessage::Meta(MetaEvent::EndOfTrack)),
+            event(5, MidiMessage::TuneRequest),
+        ]);
+        let mut data = Vec::new();
+        let warnings = file
+            .write_with_options(&mut data, &WriteOptions::default())
+            .unwrap();
+        assert_eq!(
+            warnings,
+            [ValidationWarning::EarlyEndOfTrack { track: 0, index: 0 }]
+        );
+        // The appended EndOfTrack comes after the stray one and the event following it
+        assert_eq!(
+            &data[22..],
+            [0x00, 0xFF, 0x2F, 0x00, 0x05, 0xF6, 0x00, 0xFF, 0x2F, 0x00]
+        );
+    }
+}